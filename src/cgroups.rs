@@ -0,0 +1,411 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use im::Vector;
+use crate::history::TimeSeries;
+use crate::sampler::{MetricSource, SeriesInfo, Snapshot, SourceError};
+use crate::settings::Settings;
+use crate::sysfs;
+
+pub (crate) const DEFAULT_CGROUP_ROOT: &str = "/sys/fs/cgroup";
+
+// How often the hierarchy is re-walked to pick up new and removed groups
+const WALK_INTERVAL: Duration = Duration::from_secs(5);
+
+// Resource usage of one control group over time
+#[derive(Clone, Debug)]
+pub (crate) struct Cgroup {
+    // Path below the cgroup root, e.g. "/system.slice/sshd.service", or "/" for the root group
+    pub (crate) path: String,
+    pub (crate) name: String,
+    pub (crate) parent: Option<String>,
+    // Percentage of one CPU, so a group using two cores fully shows 200%
+    pub (crate) cpu_history: TimeSeries,
+    // Bytes charged to the group; the root group has no memory.current, so this stays empty
+    pub (crate) memory_history: TimeSeries,
+    // memory.max in bytes, or None when unlimited
+    pub (crate) memory_max: Option<f64>,
+    // CPUs the cpu.max quota allows per period, or None when unlimited
+    pub (crate) cpu_max: Option<f64>,
+    // Bytes/s read and written, summed over devices
+    pub (crate) io_read_history: TimeSeries,
+    pub (crate) io_write_history: TimeSeries,
+    pub (crate) pids: Option<u64>,
+}
+
+// Parent of a group path, or None for the root group
+fn parent_path(path: &str) -> Option<String> {
+    match path.rsplit_once('/') {
+        Some((parent, name)) if !name.is_empty() => Some(if parent.is_empty() { "/" } else { parent }.to_string()),
+        _ => None,
+    }
+}
+
+impl Cgroup {
+    fn new(path: &str, history_size: usize) -> Self {
+        let name = match path.rsplit_once('/') {
+            Some((_, name)) if !name.is_empty() => name,
+            _ => "/",
+        };
+        Self {
+            path: path.to_string(),
+            name: name.to_string(),
+            parent: parent_path(path),
+            cpu_history: TimeSeries::new(history_size),
+            memory_history: TimeSeries::new(history_size),
+            memory_max: None,
+            cpu_max: None,
+            io_read_history: TimeSeries::new(history_size),
+            io_write_history: TimeSeries::new(history_size),
+            pids: None,
+        }
+    }
+
+    fn set_capacity(&mut self, capacity: usize) {
+        self.cpu_history.set_capacity(capacity);
+        self.memory_history.set_capacity(capacity);
+        self.io_read_history.set_capacity(capacity);
+        self.io_write_history.set_capacity(capacity);
+    }
+
+    // Rates over the interval between two readings of the counters
+    fn push(&mut self, current: &CgroupCounters, last: &CgroupCounters, elapsed: f64) {
+        let delta = |current: u64, last: u64| current.saturating_sub(last) as f64;
+        if let (Some(current), Some(last)) = (current.cpu_usec, last.cpu_usec) {
+            self.cpu_history.push(delta(current, last) / (elapsed * 1e6) * 100.0);
+        }
+        self.io_read_history.push(delta(current.read_bytes, last.read_bytes) / elapsed);
+        self.io_write_history.push(delta(current.write_bytes, last.write_bytes) / elapsed);
+    }
+}
+
+// Limits that apply to a group: the tightest set on it or any of its ancestors
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub (crate) struct Limits {
+    pub (crate) memory: Option<f64>,
+    pub (crate) cpus: Option<f64>,
+}
+
+pub (crate) fn effective_limits(groups: &Vector<Cgroup>, path: &str) -> Limits {
+    let tighter = |limit: Option<f64>, other: Option<f64>| match (limit, other) {
+        (Some(limit), Some(other)) => Some(limit.min(other)),
+        (limit, other) => limit.or(other),
+    };
+    let mut limits = Limits::default();
+    let mut next = Some(path.to_string());
+    while let Some(path) = next {
+        let Some(group) = groups.iter().find(|group| group.path == path) else { break };
+        limits.memory = tighter(limits.memory, group.memory_max);
+        limits.cpus = tighter(limits.cpus, group.cpu_max);
+        next = group.parent.clone();
+    }
+    limits
+}
+
+// Groups the UI currently needs readings for. Reading every group on every tick is slow on hosts
+// with many slices and containers, so the rest keep their last values until they're listed.
+#[derive(Clone, Debug, Default)]
+pub (crate) struct CgroupFocus {
+    // Groups whose children are listed in the table
+    expanded: HashSet<String>,
+    // Groups read whether or not they are listed
+    pinned: HashSet<String>,
+}
+
+impl CgroupFocus {
+    // The selected group is graphed, and limits mode needs the limits group and all its ancestors
+    pub (crate) fn new(expanded: &HashSet<String>, selected: Option<&String>, limits: Option<&String>) -> Self {
+        let mut pinned: HashSet<String> = selected.cloned().into_iter().collect();
+        let mut next = limits.cloned();
+        while let Some(path) = next {
+            next = parent_path(&path);
+            pinned.insert(path);
+        }
+        Self { expanded: expanded.clone(), pinned }
+    }
+
+    fn wants(&self, path: &str) -> bool {
+        match parent_path(path) {
+            Some(parent) => self.expanded.contains(&parent) || self.pinned.contains(path),
+            None => true,
+        }
+    }
+}
+
+// The cgroup v2 group this process runs in, from the "0::<path>" line of <proc_root>/self/cgroup.
+// Inside a container with its own cgroup namespace this is "/", the container's own group.
+pub (crate) fn own_cgroup(proc_root: impl AsRef<Path>) -> Option<String> {
+    let text = fs::read_to_string(proc_root.as_ref().join("self/cgroup")).ok()?;
+    text.lines().find_map(|line| line.strip_prefix("0::")).map(|path| path.trim().to_string())
+}
+
+// Cumulative counters of one group. Controllers that aren't enabled for a group leave their
+// files out, which reads as no usage.
+#[derive(Clone, Copy, Debug, Default)]
+struct CgroupCounters {
+    cpu_usec: Option<u64>,
+    read_bytes: u64,
+    write_bytes: u64,
+}
+
+// cpu.max holds "<quota> <period>" in microseconds, with a quota of "max" when unlimited
+fn parse_cpu_max(text: &str) -> Option<f64> {
+    let (quota, period) = text.trim().split_once(' ')?;
+    let (quota, period) = (quota.parse::<f64>().ok()?, period.parse::<f64>().ok()?);
+    (period > 0.0).then(|| quota / period)
+}
+
+// usage_usec from cpu.stat, which covers both user and system time
+fn parse_cpu_stat(text: &str) -> Option<u64> {
+    text.lines()
+        .find_map(|line| line.strip_prefix("usage_usec "))
+        .and_then(|usec| usec.trim().parse().ok())
+}
+
+// Bytes read and written over every device in io.stat, e.g. "8:0 rbytes=1024 wbytes=0 rios=1 ..."
+fn parse_io_stat(text: &str) -> (u64, u64) {
+    let mut totals = (0, 0);
+    for field in text.split_whitespace() {
+        let Some((key, value)) = field.split_once('=') else { continue };
+        let Ok(value) = value.parse::<u64>() else { continue };
+        match key {
+            "rbytes" => totals.0 += value,
+            "wbytes" => totals.1 += value,
+            _ => {}
+        }
+    }
+    totals
+}
+
+// Walks the unified (v2) hierarchy under <cgroup_root>. Hosts still on the legacy v1 hierarchy
+// don't have one, which disables the source.
+pub (crate) struct CgroupSource {
+    cgroup_root: PathBuf,
+    // Every group found by the last walk, and when that was
+    paths: Vec<String>,
+    last_walk: Option<Instant>,
+    // Counters of the groups read on the last tick
+    last_counters: HashMap<String, CgroupCounters>,
+    last_sample: Instant,
+}
+
+impl CgroupSource {
+    pub (crate) fn new(cgroup_root: impl AsRef<Path>) -> Self {
+        Self {
+            cgroup_root: cgroup_root.as_ref().to_path_buf(),
+            paths: Vec::new(),
+            last_walk: None,
+            last_counters: HashMap::new(),
+            last_sample: Instant::now(),
+        }
+    }
+
+    fn dir(&self, path: &str) -> PathBuf {
+        self.cgroup_root.join(path.trim_start_matches('/'))
+    }
+
+    // Every group below the root, parents before their children and siblings in name order
+    fn walk(&self, path: &str, paths: &mut Vec<String>) {
+        paths.push(path.to_string());
+        let Ok(entries) = fs::read_dir(self.dir(path)) else { return };
+        let mut children: Vec<String> = entries
+            .flatten()
+            .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect();
+        children.sort();
+        for child in children {
+            let child = if path == "/" { format!("/{}", child) } else { format!("{}/{}", path, child) };
+            self.walk(&child, paths);
+        }
+    }
+
+    fn read_counters(&self, dir: &Path) -> CgroupCounters {
+        let (read_bytes, write_bytes) = fs::read_to_string(dir.join("io.stat"))
+            .map(|text| parse_io_stat(&text))
+            .unwrap_or_default();
+        CgroupCounters {
+            cpu_usec: fs::read_to_string(dir.join("cpu.stat")).ok().and_then(|text| parse_cpu_stat(&text)),
+            read_bytes,
+            write_bytes,
+        }
+    }
+}
+
+impl MetricSource for CgroupSource {
+    fn name(&self) -> &'static str {
+        "cgroups"
+    }
+
+    fn init(&mut self, snapshot: &mut Snapshot, settings: &Settings) -> Result<(), SourceError> {
+        if !self.cgroup_root.join("cgroup.controllers").exists() {
+            return Err(format!("no cgroup v2 hierarchy mounted at {}", self.cgroup_root.display()).into());
+        }
+        self.sample(snapshot, settings)
+    }
+
+    fn sample(&mut self, snapshot: &mut Snapshot, settings: &Settings) -> Result<(), SourceError> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_sample).as_secs_f64();
+        self.last_sample = now;
+
+        if self.last_walk.is_none_or(|last_walk| now.duration_since(last_walk) >= WALK_INTERVAL) {
+            let mut paths = Vec::new();
+            self.walk("/", &mut paths);
+            self.paths = paths;
+            self.last_walk = Some(now);
+        }
+        // Until the UI says what it shows (and for --list-series), read every group
+        let focus = settings.cgroup_focus();
+
+        // Groups come and go as services, sessions and containers start and stop
+        let mut previous: HashMap<String, Cgroup> = snapshot.cgroups.iter()
+            .map(|group| (group.path.clone(), group.clone()))
+            .collect();
+        let mut counters = HashMap::new();
+        let mut groups = Vector::new();
+        for path in &self.paths {
+            let mut group = previous.remove(path).unwrap_or_else(|| Cgroup::new(path, settings.history_size()));
+            group.set_capacity(settings.history_size());
+            if focus.as_ref().is_some_and(|focus| !focus.wants(path)) {
+                groups.push_back(group);
+                continue;
+            }
+
+            let dir = self.dir(path);
+            if let Some(memory) = sysfs::read_f64(dir.join("memory.current")) {
+                group.memory_history.push(memory);
+            }
+            // memory.max holds "max" when the group is unlimited
+            group.memory_max = sysfs::read_f64(dir.join("memory.max"));
+            group.cpu_max = sysfs::read_string(dir.join("cpu.max")).and_then(|text| parse_cpu_max(&text));
+            group.pids = sysfs::read_u64(dir.join("pids.current"));
+
+            let current = self.read_counters(&dir);
+            // A new group, or one that wasn't read last tick, has no previous reading to take a rate from
+            if let Some(last) = self.last_counters.get(path) && elapsed > 0.0 {
+                group.push(&current, last, elapsed);
+            }
+            counters.insert(path.clone(), current);
+            groups.push_back(group);
+        }
+        snapshot.cgroups = groups;
+        self.last_counters = counters;
+        Ok(())
+    }
+
+    fn series(&self) -> Vec<SeriesInfo> {
+        self.paths.iter()
+            .flat_map(|path| [
+                SeriesInfo::new(format!("cgroup.{}.cpu", path), "%"),
+                SeriesInfo::new(format!("cgroup.{}.memory", path), "B"),
+                SeriesInfo::new(format!("cgroup.{}.read", path), "B/s"),
+                SeriesInfo::new(format!("cgroup.{}.write", path), "B/s"),
+            ])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sysfs::TempTree;
+
+    #[test]
+    fn parses_cpu_max() {
+        assert_eq!(parse_cpu_max("150000 100000\n"), Some(1.5));
+        assert_eq!(parse_cpu_max("max 100000\n"), None);
+        assert_eq!(parse_cpu_max(""), None);
+    }
+
+    // A listed group with only its limits read
+    fn group(path: &str, memory_max: Option<f64>, cpu_max: Option<f64>) -> Cgroup {
+        Cgroup { memory_max, cpu_max, ..Cgroup::new(path, 10) }
+    }
+
+    #[test]
+    fn effective_limits_are_the_tightest_on_the_way_up() {
+        let groups = Vector::from(vec![
+            group("/", None, None),
+            group("/machine.slice", Some(8e9), Some(4.0)),
+            group("/machine.slice/libpod-1.scope", Some(16e9), None),
+            group("/machine.slice/libpod-1.scope/container", None, Some(2.0)),
+        ]);
+        let limits = |path| effective_limits(&groups, path);
+
+        assert_eq!(limits("/machine.slice/libpod-1.scope/container"), Limits { memory: Some(8e9), cpus: Some(2.0) });
+        assert_eq!(limits("/machine.slice/libpod-1.scope"), Limits { memory: Some(8e9), cpus: Some(4.0) });
+        assert_eq!(limits("/"), Limits::default());
+        // A group that isn't listed, e.g. before the next walk finds it, has no known limits
+        assert_eq!(limits("/machine.slice/libpod-2.scope"), Limits::default());
+    }
+
+    #[test]
+    fn finds_own_cgroup() {
+        let proc = TempTree::new();
+        proc.write("self/cgroup", "0::/user.slice/user-1000.slice/session-2.scope\n");
+        assert_eq!(own_cgroup(proc.path()).as_deref(), Some("/user.slice/user-1000.slice/session-2.scope"));
+
+        // Inside a cgroup namespace the process is at the root of the container's own tree
+        proc.write("self/cgroup", "0::/\n");
+        assert_eq!(own_cgroup(proc.path()).as_deref(), Some("/"));
+
+        // Hybrid hosts list the v1 hierarchies before the unified one
+        proc.write("self/cgroup", "12:pids:/user.slice/user-1000.slice/session-2.scope\n\
+            4:memory:/user.slice/user-1000.slice/session-2.scope\n\
+            1:name=systemd:/user.slice/user-1000.slice/session-2.scope\n\
+            0::/user.slice/user-1000.slice/session-2.scope\n");
+        assert_eq!(own_cgroup(proc.path()).as_deref(), Some("/user.slice/user-1000.slice/session-2.scope"));
+
+        // Hosts with only cgroup v1 have no unified hierarchy to show limits from
+        proc.write("self/cgroup", "4:memory:/user.slice\n1:name=systemd:/user.slice\n");
+        assert_eq!(own_cgroup(proc.path()), None);
+    }
+
+    #[test]
+    fn parses_cpu_stat() {
+        let text = "usage_usec 48201937\nuser_usec 31520113\nsystem_usec 16681824\n\
+            nr_periods 0\nnr_throttled 0\nthrottled_usec 0\n";
+        assert_eq!(parse_cpu_stat(text), Some(48201937));
+        assert_eq!(parse_cpu_stat("nr_periods 0\n"), None);
+    }
+
+    #[test]
+    fn parses_io_stat() {
+        let text = "259:0 rbytes=1007616 wbytes=3923968 rios=74 wios=412 dbytes=0 dios=0\n\
+            8:0 rbytes=4096 wbytes=0 rios=1 wios=0 dbytes=0 dios=0\n";
+        assert_eq!(parse_io_stat(text), (1011712, 3923968));
+        assert_eq!(parse_io_stat(""), (0, 0));
+    }
+
+    #[test]
+    fn only_reads_the_groups_in_focus() {
+        let root = TempTree::new();
+        root.write("cgroup.controllers", "cpu io memory pids\n");
+        for group in ["system.slice", "system.slice/sshd.service", "user.slice", "user.slice/user-1000.slice"] {
+            root.write(&format!("{}/memory.current", group), "4096\n");
+        }
+        let mut source = CgroupSource::new(root.path());
+        let settings = Settings::new(200, 10);
+        let mut snapshot = Snapshot::default();
+        source.init(&mut snapshot, &settings).unwrap();
+
+        // Top-level groups are listed, and limits mode needs user-1000.slice and its ancestors
+        let expanded = HashSet::from(["/".to_string()]);
+        let limits = "/user.slice/user-1000.slice".to_string();
+        settings.set_cgroup_focus(CgroupFocus::new(&expanded, None, Some(&limits)));
+        source.sample(&mut snapshot, &settings).unwrap();
+
+        let samples: Vec<(&str, usize)> = snapshot.cgroups.iter()
+            .map(|group| (group.path.as_str(), group.memory_history.len()))
+            .collect();
+        assert_eq!(samples, [
+            ("/", 0),
+            ("/system.slice", 2),
+            ("/system.slice/sshd.service", 1),
+            ("/user.slice", 2),
+            ("/user.slice/user-1000.slice", 2),
+        ]);
+    }
+}
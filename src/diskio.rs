@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use im::Vector;
+use crate::history::TimeSeries;
+use crate::sampler::{MetricSource, SeriesInfo, Snapshot, SourceError};
+use crate::settings::Settings;
+use crate::sysfs;
+
+// /proc/diskstats counts in 512 byte sectors whatever the device's real sector size
+const SECTOR_SIZE: f64 = 512.0;
+
+// I/O activity of one block device
+#[derive(Clone, Debug)]
+pub (crate) struct BlockDevice {
+    pub (crate) name: String,
+    // Device-mapper name (LVM volume, LUKS mapping) for dm-N devices
+    pub (crate) dm_name: Option<String>,
+    pub (crate) read_bytes_history: TimeSeries,
+    pub (crate) write_bytes_history: TimeSeries,
+    pub (crate) read_iops_history: TimeSeries,
+    pub (crate) write_iops_history: TimeSeries,
+    // Average time an I/O completed in the interval took, queueing included, in ms
+    pub (crate) await_history: TimeSeries,
+    // Percentage of the interval the device was busy
+    pub (crate) util_history: TimeSeries,
+}
+
+impl BlockDevice {
+    fn new(name: &str, dm_name: Option<String>, history_size: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            dm_name,
+            read_bytes_history: TimeSeries::new(history_size),
+            write_bytes_history: TimeSeries::new(history_size),
+            read_iops_history: TimeSeries::new(history_size),
+            write_iops_history: TimeSeries::new(history_size),
+            await_history: TimeSeries::new(history_size),
+            util_history: TimeSeries::new(history_size),
+        }
+    }
+
+    pub (crate) fn label(&self) -> String {
+        match &self.dm_name {
+            Some(dm_name) => format!("{} ({})", self.name, dm_name),
+            None => self.name.clone(),
+        }
+    }
+
+    // Rates over the interval between two readings of the counters
+    fn push(&mut self, current: &DiskCounters, last: &DiskCounters, elapsed: f64) {
+        let delta = |current: u64, last: u64| current.saturating_sub(last) as f64;
+        let reads = delta(current.reads, last.reads);
+        let writes = delta(current.writes, last.writes);
+        let wait_ms = delta(current.ms_reading, last.ms_reading) + delta(current.ms_writing, last.ms_writing);
+
+        self.read_bytes_history.push(delta(current.sectors_read, last.sectors_read) * SECTOR_SIZE / elapsed);
+        self.write_bytes_history.push(delta(current.sectors_written, last.sectors_written) * SECTOR_SIZE / elapsed);
+        self.read_iops_history.push(reads / elapsed);
+        self.write_iops_history.push(writes / elapsed);
+        self.await_history.push(if reads + writes > 0.0 { wait_ms / (reads + writes) } else { 0.0 });
+        self.util_history.push((delta(current.ms_busy, last.ms_busy) / (elapsed * 1000.0) * 100.0).min(100.0));
+    }
+
+    fn set_capacity(&mut self, capacity: usize) {
+        self.read_bytes_history.set_capacity(capacity);
+        self.write_bytes_history.set_capacity(capacity);
+        self.read_iops_history.set_capacity(capacity);
+        self.write_iops_history.set_capacity(capacity);
+        self.await_history.set_capacity(capacity);
+        self.util_history.set_capacity(capacity);
+    }
+}
+
+// Cumulative counters of one /proc/diskstats line
+#[derive(Clone, Copy, Debug, Default)]
+struct DiskCounters {
+    reads: u64,
+    sectors_read: u64,
+    ms_reading: u64,
+    writes: u64,
+    sectors_written: u64,
+    ms_writing: u64,
+    ms_busy: u64,
+}
+
+fn parse_diskstats(text: &str) -> Vec<(String, DiskCounters)> {
+    text.lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let number = |index: usize| fields.get(index).and_then(|field| field.parse::<u64>().ok());
+            let counters = DiskCounters {
+                reads: number(3)?,
+                sectors_read: number(5)?,
+                ms_reading: number(6)?,
+                writes: number(7)?,
+                sectors_written: number(9)?,
+                ms_writing: number(10)?,
+                ms_busy: number(12)?,
+            };
+            Some((fields[2].to_string(), counters))
+        })
+        .collect()
+}
+
+// Whole block devices from <proc_root>/diskstats. Partitions are left out, as are loop and RAM
+// disks, which only duplicate the I/O of the devices backing them.
+pub (crate) struct DiskIoSource {
+    proc_root: PathBuf,
+    sys_root: PathBuf,
+    last_counters: HashMap<String, DiskCounters>,
+    last_sample: Instant,
+}
+
+impl DiskIoSource {
+    pub (crate) fn new(proc_root: impl AsRef<Path>, sys_root: impl AsRef<Path>) -> Self {
+        Self {
+            proc_root: proc_root.as_ref().to_path_buf(),
+            sys_root: sys_root.as_ref().to_path_buf(),
+            last_counters: HashMap::new(),
+            last_sample: Instant::now(),
+        }
+    }
+
+    fn read_counters(&self) -> Result<Vec<(String, DiskCounters)>, SourceError> {
+        let text = fs::read_to_string(self.proc_root.join("diskstats"))?;
+        Ok(parse_diskstats(&text).into_iter()
+            .filter(|(name, _)| !name.starts_with("loop") && !name.starts_with("ram") && !name.starts_with("zram"))
+            .filter(|(name, _)| self.sys_root.join("block").join(name).exists())
+            .collect())
+    }
+
+    fn dm_name(&self, name: &str) -> Option<String> {
+        sysfs::read_string(self.sys_root.join("block").join(name).join("dm/name"))
+    }
+}
+
+impl MetricSource for DiskIoSource {
+    fn name(&self) -> &'static str {
+        "diskio"
+    }
+
+    fn init(&mut self, snapshot: &mut Snapshot, settings: &Settings) -> Result<(), SourceError> {
+        let counters = self.read_counters()?;
+        snapshot.disks = counters.iter()
+            .map(|(name, _)| BlockDevice::new(name, self.dm_name(name), settings.history_size()))
+            .collect();
+        self.last_counters = counters.into_iter().collect();
+        self.last_sample = Instant::now();
+        Ok(())
+    }
+
+    fn sample(&mut self, snapshot: &mut Snapshot, settings: &Settings) -> Result<(), SourceError> {
+        let counters = self.read_counters()?;
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_sample).as_secs_f64();
+        self.last_sample = now;
+
+        // Devices appear and disappear with hotplugged drives and LVM activation
+        let mut disks = Vector::new();
+        for (name, current) in &counters {
+            let mut disk = snapshot.disks.iter()
+                .find(|disk| disk.name == *name)
+                .cloned()
+                .unwrap_or_else(|| BlockDevice::new(name, self.dm_name(name), settings.history_size()));
+            disk.set_capacity(settings.history_size());
+
+            // A new device has no previous reading to take a rate from
+            if let Some(last) = self.last_counters.get(name) && elapsed > 0.0 {
+                disk.push(current, last, elapsed);
+            }
+            disks.push_back(disk);
+        }
+        snapshot.disks = disks;
+
+        self.last_counters = counters.into_iter().collect();
+        Ok(())
+    }
+
+    fn series(&self) -> Vec<SeriesInfo> {
+        let mut names: Vec<&String> = self.last_counters.keys().collect();
+        names.sort();
+        names.into_iter()
+            .flat_map(|name| [
+                SeriesInfo::new(format!("disk.{}.read", name), "B/s"),
+                SeriesInfo::new(format!("disk.{}.write", name), "B/s"),
+                SeriesInfo::new(format!("disk.{}.read_iops", name), "IO/s"),
+                SeriesInfo::new(format!("disk.{}.write_iops", name), "IO/s"),
+                SeriesInfo::new(format!("disk.{}.await", name), "ms"),
+                SeriesInfo::new(format!("disk.{}.util", name), "%"),
+            ])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_diskstats() {
+        // Lines from a 5.x kernel (with discard and flush fields) and an old 4.x one without them
+        let text = concat!(
+            "   8       0 sda 41866 9418 2763506 23144 22155 28830 1427648 58290 0 40732 88612 0 0 0 0 2307 7177\n",
+            "   8       1 sda1 41720 9418 2756786 23091 22155 28830 1427648 58290 0 40700 81381\n",
+            " 259       0 nvme0n1 1123 0 68398 201 51 41 1176 31 0 276 233 0 0 0 0 0 0\n",
+            "   7       0 loop0 bad\n",
+        );
+        let disks = parse_diskstats(text);
+        let names: Vec<&str> = disks.iter().map(|(name, _)| name.as_str()).collect();
+        assert_eq!(names, ["sda", "sda1", "nvme0n1"]);
+
+        let sda = &disks[0].1;
+        assert_eq!((sda.reads, sda.sectors_read, sda.ms_reading), (41866, 2763506, 23144));
+        assert_eq!((sda.writes, sda.sectors_written, sda.ms_writing), (22155, 1427648, 58290));
+        assert_eq!(sda.ms_busy, 40732);
+        assert_eq!(disks[1].1.ms_busy, 40700);
+    }
+}
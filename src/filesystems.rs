@@ -0,0 +1,97 @@
+use std::ffi::CString;
+use std::os::unix::ffi::OsStrExt;
+use std::path::Path;
+use druid::{Data, Lens};
+use sysinfo::{DiskRefreshKind, Disks};
+use crate::sampler::{MetricSource, SeriesInfo, Snapshot, SourceError};
+use crate::settings::Settings;
+
+// One mounted filesystem
+#[derive(Clone, Data, Lens, Debug)]
+pub (crate) struct Filesystem {
+    pub (crate) mount_point: String,
+    pub (crate) device: String,
+    pub (crate) fs_type: String,
+    pub (crate) total: f64,
+    pub (crate) available: f64,
+    // Inode counts, or None for filesystems such as btrfs that allocate inodes dynamically
+    pub (crate) inodes_total: Option<u64>,
+    pub (crate) inodes_free: Option<u64>,
+}
+
+impl Filesystem {
+    pub (crate) fn used(&self) -> f64 {
+        (self.total - self.available).max(0.0)
+    }
+
+    pub (crate) fn used_percent(&self) -> f64 {
+        if self.total > 0.0 { self.used() / self.total * 100.0 } else { 0.0 }
+    }
+
+    pub (crate) fn inodes_used_percent(&self) -> Option<f64> {
+        match (self.inodes_total, self.inodes_free) {
+            (Some(total), Some(free)) if total > 0 => Some(total.saturating_sub(free) as f64 / total as f64 * 100.0),
+            _ => None,
+        }
+    }
+}
+
+// Inode totals from statvfs(3), which sysinfo doesn't report
+fn inode_counts(mount_point: &Path) -> Option<(u64, u64)> {
+    let path = CString::new(mount_point.as_os_str().as_bytes()).ok()?;
+    // SAFETY: statvfs is plain data, and is only read after the call has filled it in
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 || stat.f_files == 0 {
+        return None;
+    }
+    Some((stat.f_files as u64, stat.f_ffree as u64))
+}
+
+// Mounted filesystems from sysinfo, with inode usage from statvfs
+pub (crate) struct FilesystemSource {
+    disks: Disks,
+}
+
+impl FilesystemSource {
+    pub (crate) fn new() -> Self {
+        Self { disks: Disks::new() }
+    }
+}
+
+impl MetricSource for FilesystemSource {
+    fn name(&self) -> &'static str {
+        "filesystems"
+    }
+
+    fn init(&mut self, snapshot: &mut Snapshot, settings: &Settings) -> Result<(), SourceError> {
+        self.sample(snapshot, settings)
+    }
+
+    fn sample(&mut self, snapshot: &mut Snapshot, _settings: &Settings) -> Result<(), SourceError> {
+        // Also picks up filesystems mounted since the last refresh
+        self.disks.refresh_specifics(true, DiskRefreshKind::nothing().with_storage());
+
+        let mut filesystems: Vec<Filesystem> = self.disks.list().iter()
+            .map(|disk| {
+                let inodes = inode_counts(disk.mount_point());
+                Filesystem {
+                    mount_point: disk.mount_point().to_string_lossy().into_owned(),
+                    device: disk.name().to_string_lossy().into_owned(),
+                    fs_type: disk.file_system().to_string_lossy().into_owned(),
+                    total: disk.total_space() as f64,
+                    available: disk.available_space() as f64,
+                    inodes_total: inodes.map(|(total, _)| total),
+                    inodes_free: inodes.map(|(_, free)| free),
+                }
+            })
+            .collect();
+        filesystems.sort_by(|a, b| a.mount_point.cmp(&b.mount_point));
+        snapshot.filesystems = filesystems.into_iter().collect();
+        Ok(())
+    }
+
+    fn series(&self) -> Vec<SeriesInfo> {
+        // Filesystem usage is shown as a table rather than a history
+        Vec::new()
+    }
+}
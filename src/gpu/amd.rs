@@ -0,0 +1,184 @@
+use std::path::{Path, PathBuf};
+use crate::gpu::{GpuBackend, GpuDevice, GpuError, GpuSample, MemoryUsage};
+use crate::sysfs;
+
+const AMD_VENDOR_ID: &str = "0x1002";
+
+// amdgpu cards found under <root>/class/drm; the root is normally /sys
+pub (crate) struct AmdBackend {
+    // DRM card number and device directory of each card
+    cards: Vec<(u32, PathBuf)>,
+}
+
+impl AmdBackend {
+    pub (crate) fn new(root: impl AsRef<Path>) -> Self {
+        // card0, card1, ... but not connectors such as card0-DP-1
+        let cards = sysfs::numbered_entries(root.as_ref().join("class/drm"), "card", "")
+            .into_iter()
+            .map(|(number, card)| (number, card.join("device")))
+            .filter(|(_, device)| sysfs::read_string(device.join("vendor")).as_deref() == Some(AMD_VENDOR_ID))
+            .filter(|(_, device)| device.join("gpu_busy_percent").exists())
+            .collect();
+        Self { cards }
+    }
+}
+
+impl GpuBackend for AmdBackend {
+    fn device_count(&self) -> Result<u32, GpuError> {
+        Ok(self.cards.len() as u32)
+    }
+
+    fn device(&self, index: u32) -> Result<Box<dyn GpuDevice>, GpuError> {
+        let (card, path) = self.cards.get(index as usize)
+            .ok_or_else(|| format!("no amdgpu card at index {}", index))?
+            .clone();
+        // amdgpu exposes exactly one hwmon node per card
+        let hwmon = sysfs::hwmon_dir(&path);
+        let fans = hwmon.as_ref()
+            .map(|hwmon| sysfs::numbered_entries(hwmon, "fan", "_input"))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(_, fan)| fan)
+            .collect();
+        let name = sysfs::read_string(path.join("product_name"))
+            .filter(|name| !name.is_empty())
+            .unwrap_or_else(|| format!("AMD Radeon (card{})", card));
+
+        Ok(Box::new(AmdDevice { path, hwmon, fans, name }))
+    }
+}
+
+struct AmdDevice {
+    path: PathBuf,
+    hwmon: Option<PathBuf>,
+    fans: Vec<PathBuf>,
+    name: String,
+}
+
+impl GpuDevice for AmdDevice {
+    fn brand(&self) -> String {
+        "AMD".to_string()
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn num_fans(&self) -> u32 {
+        self.fans.len() as u32
+    }
+
+    fn sample(&mut self) -> GpuSample {
+        let used = sysfs::read_u64(self.path.join("mem_info_vram_used"));
+        let total = sysfs::read_u64(self.path.join("mem_info_vram_total"));
+
+        // hwmon reports millidegrees Celsius and microwatts; temp1 is the edge sensor
+        let temperature = self.hwmon.as_ref()
+            .and_then(|hwmon| sysfs::numbered_entries(hwmon, "temp", "_input").into_iter().next())
+            .and_then(|(_, temp)| sysfs::read_f64(temp))
+            .map(|millidegrees| millidegrees / 1000.0);
+        // Newer kernels replace power1_average with power1_input
+        let power = self.hwmon.as_ref()
+            .and_then(|hwmon| {
+                sysfs::read_f64(hwmon.join("power1_average"))
+                    .or_else(|| sysfs::read_f64(hwmon.join("power1_input")))
+            })
+            .map(|microwatts| microwatts / 1_000_000.0);
+        let hwmon_value = |file: &str, scale: f64| {
+            self.hwmon.as_ref().and_then(|hwmon| sysfs::read_f64(hwmon.join(file))).map(|v| v / scale)
+        };
+
+        GpuSample {
+            temperature,
+            fan_speeds: self.fans.iter().map(sysfs::read_f64).collect(),
+            utilization: sysfs::read_f64(self.path.join("gpu_busy_percent")),
+            memory_utilization: sysfs::read_f64(self.path.join("mem_busy_percent")),
+            memory: used.zip(total).map(|(used, total)| MemoryUsage { used, total }),
+            power,
+            power_limit: hwmon_value("power1_cap", 1_000_000.0),
+            // freq1 is the shader clock and freq2 the memory clock, both in Hz
+            graphics_clock: hwmon_value("freq1_input", 1_000_000.0),
+            memory_clock: hwmon_value("freq2_input", 1_000_000.0),
+            ..GpuSample::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sysfs::TempTree;
+
+    #[test]
+    fn reads_cards_from_a_sysfs_tree() {
+        let sys = TempTree::new();
+        sys.write("class/drm/card0/device/vendor", "0x1002\n")
+            .write("class/drm/card0/device/product_name", "\n")
+            .write("class/drm/card0/device/gpu_busy_percent", "42\n")
+            .write("class/drm/card0/device/mem_busy_percent", "7\n")
+            .write("class/drm/card0/device/mem_info_vram_used", "1073741824\n")
+            .write("class/drm/card0/device/mem_info_vram_total", "4294967296\n")
+            .write("class/drm/card0/device/hwmon/hwmon0/temp1_input", "65000\n")
+            .write("class/drm/card0/device/hwmon/hwmon0/temp2_input", "80000\n")
+            .write("class/drm/card0/device/hwmon/hwmon0/fan1_input", "1250\n")
+            .write("class/drm/card0/device/hwmon/hwmon0/power1_average", "45500000\n")
+            .write("class/drm/card0/device/hwmon/hwmon0/power1_cap", "150000000\n")
+            .write("class/drm/card0/device/hwmon/hwmon0/freq1_input", "1800000000\n")
+            .write("class/drm/card0/device/hwmon/hwmon0/freq2_input", "1000000000\n")
+            // A connector of the same card, which has the card's files behind its device link
+            .write("class/drm/card0-DP-1/status", "connected\n")
+            .write("class/drm/card0-DP-1/device/vendor", "0x1002\n")
+            .write("class/drm/card0-DP-1/device/gpu_busy_percent", "0\n")
+            .write("class/drm/card1/device/vendor", "0x10de\n");
+
+        let backend = AmdBackend::new(sys.path());
+        assert_eq!(backend.device_count().unwrap(), 1);
+
+        let mut device = backend.device(0).unwrap();
+        assert_eq!(device.name(), "AMD Radeon (card0)");
+        assert_eq!(device.num_fans(), 1);
+
+        let sample = device.sample();
+        assert_eq!(sample.temperature, Some(65.0));
+        assert_eq!(sample.fan_speeds, [Some(1250.0)]);
+        assert_eq!(sample.utilization, Some(42.0));
+        assert_eq!(sample.memory_utilization, Some(7.0));
+        let memory = sample.memory.unwrap();
+        assert_eq!((memory.used, memory.total), (1 << 30, 4 << 30));
+        assert_eq!(sample.power, Some(45.5));
+        assert_eq!(sample.power_limit, Some(150.0));
+        assert_eq!(sample.graphics_clock, Some(1800.0));
+        assert_eq!(sample.memory_clock, Some(1000.0));
+    }
+
+    #[test]
+    fn falls_back_to_power_input() {
+        let sys = TempTree::new();
+        sys.write("class/drm/card0/device/vendor", "0x1002\n")
+            .write("class/drm/card0/device/product_name", "AMD Radeon RX 7600\n")
+            .write("class/drm/card0/device/gpu_busy_percent", "0\n")
+            .write("class/drm/card0/device/hwmon/hwmon3/power1_input", "12000000\n");
+
+        let mut device = AmdBackend::new(sys.path()).device(0).unwrap();
+        assert_eq!(device.name(), "AMD Radeon RX 7600");
+        assert_eq!(device.num_fans(), 0);
+
+        let sample = device.sample();
+        assert_eq!(sample.power, Some(12.0));
+        assert_eq!(sample.temperature, None);
+        assert!(sample.memory.is_none());
+    }
+
+    #[test]
+    fn names_unnamed_cards_by_drm_number() {
+        // An integrated Intel card0 ahead of a discrete AMD card1
+        let sys = TempTree::new();
+        sys.write("class/drm/card0/device/vendor", "0x8086\n")
+            .write("class/drm/card1/device/vendor", "0x1002\n")
+            .write("class/drm/card1/device/gpu_busy_percent", "0\n");
+
+        let backend = AmdBackend::new(sys.path());
+        assert_eq!(backend.device_count().unwrap(), 1);
+        assert_eq!(backend.device(0).unwrap().name(), "AMD Radeon (card1)");
+    }
+}
@@ -0,0 +1,188 @@
+use crate::gpu::{GpuBackend, GpuDevice, GpuError, GpuProcess, GpuSample, MemoryUsage};
+
+// In-process backend that replays scripted readings, for running without GPU hardware
+pub (crate) struct FakeBackend {
+    devices: Vec<FakeDevice>,
+}
+
+impl FakeBackend {
+    pub (crate) fn new(devices: Vec<FakeDevice>) -> Self {
+        Self { devices }
+    }
+
+    // Two cards with smoothly varying readings, used by `--fake-gpu`
+    pub (crate) fn demo() -> Self {
+        let wave = |base: f64, amplitude: f64, period: usize| -> Vec<f64> {
+            (0..period)
+                .map(|i| base + amplitude * (i as f64 / period as f64 * std::f64::consts::TAU).sin())
+                .collect()
+        };
+        let total = 8 * 1024 * 1024 * 1024;
+        let memory = |fraction: Vec<f64>| fraction.iter().map(|f| (f * total as f64) as u64).collect();
+        // Attribute the fake load to this process so the process list has a real name to show
+        let own_pid = std::process::id();
+
+        Self::new(vec![
+            FakeDevice::new("Fake GPU 0")
+                .temperatures(wave(60.0, 15.0, 120))
+                .fan_speeds(vec![wave(1500.0, 600.0, 80), wave(1400.0, 500.0, 100)])
+                .utilization(wave(50.0, 45.0, 60), wave(30.0, 20.0, 45))
+                .memory(memory(wave(0.5, 0.3, 90)), total)
+                .power(wave(180.0, 120.0, 60), 320.0)
+                .clocks(wave(1600.0, 400.0, 60), wave(9000.0, 500.0, 30))
+                .pcie(wave(4e9, 3e9, 50), wave(1e9, 8e8, 70))
+                .processes(vec![GpuProcess { pid: own_pid, used_mem: Some(512 * 1024 * 1024), sm_util: Some(12.0) }]),
+            FakeDevice::new("Fake GPU 1")
+                .temperatures(wave(45.0, 10.0, 70))
+                .fan_speeds(vec![wave(1000.0, 300.0, 50)])
+                .utilization(wave(30.0, 25.0, 40), wave(15.0, 10.0, 55))
+                .memory(memory(wave(0.3, 0.2, 110)), total)
+                .power(wave(90.0, 50.0, 40), 200.0)
+                .clocks(wave(1200.0, 300.0, 40), wave(7000.0, 300.0, 35))
+                .pcie(wave(1e9, 8e8, 30), wave(4e8, 3e8, 45)),
+        ])
+    }
+}
+
+impl GpuBackend for FakeBackend {
+    fn device_count(&self) -> Result<u32, GpuError> {
+        Ok(self.devices.len() as u32)
+    }
+
+    fn device(&self, index: u32) -> Result<Box<dyn GpuDevice>, GpuError> {
+        match self.devices.get(index as usize) {
+            Some(device) => Ok(Box::new(device.clone())),
+            None => Err(format!("no fake GPU at index {}", index).into()),
+        }
+    }
+}
+
+// Each scripted sequence is replayed in a loop; an empty sequence reports "not supported"
+#[derive(Clone, Debug)]
+pub (crate) struct FakeDevice {
+    name: String,
+    temperatures: Vec<f64>,
+    fan_speeds: Vec<Vec<f64>>,
+    utilization: Vec<f64>,
+    memory_utilization: Vec<f64>,
+    used_mem: Vec<u64>,
+    total_mem: u64,
+    power: Vec<f64>,
+    power_limit: Option<f64>,
+    graphics_clock: Vec<f64>,
+    memory_clock: Vec<f64>,
+    pcie_rx: Vec<f64>,
+    pcie_tx: Vec<f64>,
+    processes: Vec<GpuProcess>,
+    tick: usize,
+}
+
+impl FakeDevice {
+    pub (crate) fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            temperatures: Vec::new(),
+            fan_speeds: Vec::new(),
+            utilization: Vec::new(),
+            memory_utilization: Vec::new(),
+            used_mem: Vec::new(),
+            total_mem: 0,
+            power: Vec::new(),
+            power_limit: None,
+            graphics_clock: Vec::new(),
+            memory_clock: Vec::new(),
+            pcie_rx: Vec::new(),
+            pcie_tx: Vec::new(),
+            processes: Vec::new(),
+            tick: 0,
+        }
+    }
+
+    pub (crate) fn temperatures(mut self, values: Vec<f64>) -> Self {
+        self.temperatures = values;
+        self
+    }
+
+    pub (crate) fn fan_speeds(mut self, fans: Vec<Vec<f64>>) -> Self {
+        self.fan_speeds = fans;
+        self
+    }
+
+    pub (crate) fn utilization(mut self, sm: Vec<f64>, memory: Vec<f64>) -> Self {
+        self.utilization = sm;
+        self.memory_utilization = memory;
+        self
+    }
+
+    pub (crate) fn memory(mut self, used: Vec<u64>, total: u64) -> Self {
+        self.used_mem = used;
+        self.total_mem = total;
+        self
+    }
+
+    pub (crate) fn power(mut self, watts: Vec<f64>, limit: f64) -> Self {
+        self.power = watts;
+        self.power_limit = Some(limit);
+        self
+    }
+
+    pub (crate) fn clocks(mut self, graphics: Vec<f64>, memory: Vec<f64>) -> Self {
+        self.graphics_clock = graphics;
+        self.memory_clock = memory;
+        self
+    }
+
+    // PCIe throughput in bytes per second
+    pub (crate) fn pcie(mut self, rx: Vec<f64>, tx: Vec<f64>) -> Self {
+        self.pcie_rx = rx;
+        self.pcie_tx = tx;
+        self
+    }
+
+    pub (crate) fn processes(mut self, processes: Vec<GpuProcess>) -> Self {
+        self.processes = processes;
+        self
+    }
+
+    fn at<T: Copy>(values: &[T], tick: usize) -> Option<T> {
+        if values.is_empty() {
+            None
+        } else {
+            Some(values[tick % values.len()])
+        }
+    }
+}
+
+impl GpuDevice for FakeDevice {
+    fn brand(&self) -> String {
+        "Fake".to_string()
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn num_fans(&self) -> u32 {
+        self.fan_speeds.len() as u32
+    }
+
+    fn sample(&mut self) -> GpuSample {
+        let tick = self.tick;
+        self.tick += 1;
+
+        GpuSample {
+            temperature: Self::at(&self.temperatures, tick),
+            fan_speeds: self.fan_speeds.iter().map(|fan| Self::at(fan, tick)).collect(),
+            utilization: Self::at(&self.utilization, tick),
+            memory_utilization: Self::at(&self.memory_utilization, tick),
+            memory: Self::at(&self.used_mem, tick).map(|used| MemoryUsage { used, total: self.total_mem }),
+            power: Self::at(&self.power, tick),
+            power_limit: self.power_limit,
+            graphics_clock: Self::at(&self.graphics_clock, tick),
+            memory_clock: Self::at(&self.memory_clock, tick),
+            pcie_rx: Self::at(&self.pcie_rx, tick),
+            pcie_tx: Self::at(&self.pcie_tx, tick),
+            processes: self.processes.clone(),
+        }
+    }
+}
@@ -0,0 +1,375 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+use crate::gpu::{GpuBackend, GpuDevice, GpuError, GpuSample};
+use crate::history::percent;
+use crate::sysfs;
+
+const INTEL_VENDOR_ID: &str = "0x8086";
+
+// i915/xe cards found under <sys_root>/class/drm; engine activity comes from the DRM client
+// stats each process exposes in <proc_root>/<pid>/fdinfo
+pub (crate) struct IntelBackend {
+    cards: Vec<PathBuf>,
+    clients: Arc<Mutex<ClientScan>>,
+}
+
+impl IntelBackend {
+    pub (crate) fn new(sys_root: impl AsRef<Path>, proc_root: impl AsRef<Path>) -> Self {
+        let cards = sysfs::numbered_entries(sys_root.as_ref().join("class/drm"), "card", "")
+            .into_iter()
+            .map(|(_, card)| card)
+            .filter(|card| sysfs::read_string(card.join("device/vendor")).as_deref() == Some(INTEL_VENDOR_ID))
+            .filter(|card| matches!(driver_name(card).as_deref(), Some("i915") | Some("xe")))
+            .collect();
+        Self { cards, clients: Arc::new(Mutex::new(ClientScan::new(proc_root))) }
+    }
+}
+
+fn driver_name(card: &Path) -> Option<String> {
+    let driver = fs::read_link(card.join("device/driver")).ok()?;
+    Some(driver.file_name()?.to_string_lossy().into_owned())
+}
+
+impl GpuBackend for IntelBackend {
+    fn device_count(&self) -> Result<u32, GpuError> {
+        Ok(self.cards.len() as u32)
+    }
+
+    fn device(&self, index: u32) -> Result<Box<dyn GpuDevice>, GpuError> {
+        let card = self.cards.get(index as usize)
+            .ok_or_else(|| format!("no Intel card at index {}", index))?
+            .clone();
+        // fdinfo identifies the card by its PCI address, which is the name of the device directory
+        let pdev = fs::canonicalize(card.join("device"))?
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .ok_or("Intel card has no PCI address")?;
+        let hwmon = sysfs::hwmon_dir(card.join("device"));
+        let fans = hwmon.as_ref()
+            .map(|hwmon| sysfs::numbered_entries(hwmon, "fan", "_input"))
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(_, fan)| fan)
+            .collect();
+        let name = match driver_name(&card).as_deref() {
+            Some("xe") => format!("Intel Graphics (xe, {})", pdev),
+            _ => format!("Intel Graphics (i915, {})", pdev),
+        };
+
+        Ok(Box::new(IntelDevice {
+            card,
+            clients: self.clients.clone(),
+            scans_used: 0,
+            pdev,
+            hwmon,
+            fans,
+            name,
+            last_clients: None,
+            last_energy: None,
+            last_sample: Instant::now(),
+        }))
+    }
+}
+
+struct IntelDevice {
+    card: PathBuf,
+    clients: Arc<Mutex<ClientScan>>,
+    // Generation of the last client scan this card read
+    scans_used: u64,
+    pdev: String,
+    hwmon: Option<PathBuf>,
+    fans: Vec<PathBuf>,
+    name: String,
+    // None until the first sample, which only records the baseline
+    last_clients: Option<Clients>,
+    last_energy: Option<f64>,
+    last_sample: Instant,
+}
+
+// Busy time of one engine class for one DRM client. i915 reports busy nanoseconds; xe reports
+// busy GPU cycles alongside the total cycles elapsed.
+#[derive(Clone, Copy, Debug, Default)]
+struct EngineCounter {
+    busy: u64,
+    total_cycles: Option<u64>,
+}
+
+// Engine counters of the DRM clients of one card, by client id
+type Clients = HashMap<u64, HashMap<String, EngineCounter>>;
+
+// DRM clients of every Intel card, from one walk over <proc_root>/*/fdinfo that all the cards share
+struct ClientScan {
+    proc_root: PathBuf,
+    // Counts the walks, so each card can tell whether it has already read the latest one
+    generation: u64,
+    // Clients by the PCI address of their card
+    cards: HashMap<String, Clients>,
+}
+
+impl ClientScan {
+    fn new(proc_root: impl AsRef<Path>) -> Self {
+        Self { proc_root: proc_root.as_ref().to_path_buf(), generation: 0, cards: HashMap::new() }
+    }
+
+    // Clients of the card at `pdev`. Every card samples once per tick, so the first card to find
+    // it has already read the latest walk starts a new one and the rest of the tick reuses it.
+    fn clients(&mut self, pdev: &str, used: &mut u64) -> Clients {
+        if *used == self.generation {
+            self.cards = scan_clients(&self.proc_root);
+            self.generation += 1;
+        }
+        *used = self.generation;
+        self.cards.get(pdev).cloned().unwrap_or_default()
+    }
+}
+
+fn scan_clients(proc_root: &Path) -> HashMap<String, Clients> {
+    let mut cards: HashMap<String, Clients> = HashMap::new();
+
+    let Ok(processes) = fs::read_dir(proc_root) else { return cards };
+    for process in processes.flatten() {
+        if !process.file_name().to_string_lossy().chars().all(|c| c.is_ascii_digit()) {
+            continue;
+        }
+        // Other users' fds are unreadable without privileges; skip those processes
+        let Ok(fds) = fs::read_dir(process.path().join("fd")) else { continue };
+        for fd in fds.flatten() {
+            // Only DRM fds carry usage stats, so skip everything else without reading its fdinfo
+            let is_drm = fs::read_link(fd.path()).is_ok_and(|target| target.starts_with("/dev/dri"));
+            if !is_drm {
+                continue;
+            }
+            let Ok(text) = fs::read_to_string(process.path().join("fdinfo").join(fd.file_name())) else { continue };
+            let Some(client) = parse_fdinfo(&text) else { continue };
+            // A client shared between several fds or processes must only be counted once
+            cards.entry(client.pdev).or_default().entry(client.id).or_insert(client.engines);
+        }
+    }
+    cards
+}
+
+// Busy time of each engine between two scans, with the cycles elapsed when the driver reports
+// them. Only clients in both scans count: a new client's counters include time from before it
+// was first seen, and an exited client's counters are gone, so summing every client and taking
+// the difference would jump to 100% or drop to 0% whenever a client comes or goes.
+fn engine_deltas(clients: &Clients, last_clients: &Clients) -> HashMap<String, (f64, Option<f64>)> {
+    let mut deltas: HashMap<String, (f64, Option<f64>)> = HashMap::new();
+    for (id, engines) in clients {
+        let Some(last_engines) = last_clients.get(id) else { continue };
+        for (engine, counter) in engines {
+            let Some(last) = last_engines.get(engine) else { continue };
+            let delta = deltas.entry(engine.clone()).or_default();
+            delta.0 += counter.busy.saturating_sub(last.busy) as f64;
+            if let (Some(total), Some(last_total)) = (counter.total_cycles, last.total_cycles) {
+                // Every client of a card sees the same cycle counter
+                delta.1 = Some(total.saturating_sub(last_total) as f64);
+            }
+        }
+    }
+    deltas
+}
+
+impl IntelDevice {
+    fn frequency(&self) -> Option<f64> {
+        sysfs::read_f64(self.card.join("gt_act_freq_mhz"))
+            .or_else(|| sysfs::read_f64(self.card.join("device/tile0/gt0/freq0/act_freq")))
+    }
+}
+
+struct DrmClient {
+    pdev: String,
+    id: u64,
+    engines: HashMap<String, EngineCounter>,
+}
+
+// Parse the DRM usage stats of one fdinfo file, returning None for non-DRM file descriptors
+fn parse_fdinfo(text: &str) -> Option<DrmClient> {
+    let mut pdev = None;
+    let mut id = None;
+    let mut engines: HashMap<String, EngineCounter> = HashMap::new();
+
+    for line in text.lines() {
+        let Some((key, value)) = line.split_once(':') else { continue };
+        let value = value.trim();
+        let number = || value.split_whitespace().next().and_then(|n| n.parse::<u64>().ok());
+
+        if key == "drm-pdev" {
+            pdev = Some(value.to_string());
+        } else if key == "drm-client-id" {
+            id = number();
+        } else if key.starts_with("drm-engine-capacity-") {
+            continue;
+        } else if let Some(engine) = key.strip_prefix("drm-engine-") {
+            engines.entry(engine.to_string()).or_default().busy = number().unwrap_or(0);
+        } else if let Some(engine) = key.strip_prefix("drm-total-cycles-") {
+            engines.entry(engine.to_string()).or_default().total_cycles = number();
+        } else if let Some(engine) = key.strip_prefix("drm-cycles-") {
+            engines.entry(engine.to_string()).or_default().busy = number().unwrap_or(0);
+        }
+    }
+
+    Some(DrmClient { pdev: pdev?, id: id?, engines })
+}
+
+impl GpuDevice for IntelDevice {
+    fn brand(&self) -> String {
+        "Intel".to_string()
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn num_fans(&self) -> u32 {
+        self.fans.len() as u32
+    }
+
+    fn sample(&mut self) -> GpuSample {
+        let now = Instant::now();
+        let elapsed_ns = now.duration_since(self.last_sample).as_nanos() as f64;
+        let clients = self.clients.lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .clients(&self.pdev, &mut self.scans_used);
+
+        // Report the busiest engine, as intel_gpu_top does for its overall figure
+        let utilization = self.last_clients.as_ref().map(|last_clients| {
+            engine_deltas(&clients, last_clients)
+                .into_values()
+                .map(|(busy, cycles)| percent(busy, cycles.unwrap_or(elapsed_ns)))
+                .reduce(f64::max)
+                .unwrap_or(0.0)
+        });
+
+        // Discrete cards expose a cumulative energy counter in microjoules rather than power
+        let (power, energy) = match &self.hwmon {
+            Some(hwmon) => match sysfs::read_f64(hwmon.join("energy1_input")) {
+                Some(energy) => {
+                    let power = self.last_energy
+                        .filter(|_| elapsed_ns > 0.0)
+                        .map(|last| (energy - last).max(0.0) / 1_000_000.0 / (elapsed_ns / 1e9));
+                    (power, Some(energy))
+                }
+                None => (sysfs::read_f64(hwmon.join("power1_input")).map(|uw| uw / 1_000_000.0), None),
+            },
+            None => (None, None),
+        };
+        let temperature = self.hwmon.as_ref()
+            .and_then(|hwmon| sysfs::numbered_entries(hwmon, "temp", "_input").into_iter().next())
+            .and_then(|(_, temp)| sysfs::read_f64(temp))
+            .map(|millidegrees| millidegrees / 1000.0);
+
+        self.last_clients = Some(clients);
+        self.last_energy = energy;
+        self.last_sample = now;
+
+        GpuSample {
+            temperature,
+            fan_speeds: self.fans.iter().map(sysfs::read_f64).collect(),
+            utilization,
+            memory: None,
+            power,
+            graphics_clock: self.frequency(),
+            ..GpuSample::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sysfs::TempTree;
+
+    // Two xe cards in a fake /sys tree, at 0000:03:00.0 and 0000:04:00.0
+    fn sys_tree() -> TempTree {
+        let sys = TempTree::new();
+        for (card, pdev) in [("card0", "0000:03:00.0"), ("card1", "0000:04:00.0")] {
+            let device = format!("devices/pci0000:00/{}", pdev);
+            sys.write(&format!("{}/vendor", device), "0x8086\n")
+                .symlink(&format!("{}/driver", device), "../../../bus/pci/drivers/xe")
+                .symlink(&format!("class/drm/{}/device", card), &format!("../../../{}", device));
+        }
+        sys
+    }
+
+    // fdinfo of an xe client that has kept the render engine busy for `busy` of `total` cycles
+    fn fdinfo(pdev: &str, id: u64, busy: u64, total: u64) -> String {
+        format!(
+            "drm-driver:\txe\ndrm-client-id:\t{}\ndrm-pdev:\t{}\ndrm-cycles-rcs:\t{}\ndrm-total-cycles-rcs:\t{}\n",
+            id, pdev, busy, total
+        )
+    }
+
+    #[test]
+    fn parses_i915_fdinfo() {
+        let text = "pos:\t0\nflags:\t02100002\nmnt_id:\t26\nino:\t1081\ndrm-driver:\ti915\n\
+            drm-client-id:\t42\ndrm-pdev:\t0000:00:02.0\ndrm-total-local0:\t0\n\
+            drm-engine-render:\t25662044495 ns\ndrm-engine-copy:\t0 ns\ndrm-engine-video:\t117453 ns\n\
+            drm-engine-capacity-video:\t2\ndrm-engine-video-enhance:\t0 ns\n";
+        let client = parse_fdinfo(text).unwrap();
+
+        assert_eq!(client.pdev, "0000:00:02.0");
+        assert_eq!(client.id, 42);
+        assert_eq!(client.engines.len(), 4);
+        assert_eq!(client.engines["render"].busy, 25662044495);
+        assert_eq!(client.engines["video"].busy, 117453);
+        assert_eq!(client.engines["render"].total_cycles, None);
+        assert!(!client.engines.contains_key("capacity-video"));
+    }
+
+    #[test]
+    fn parses_xe_fdinfo() {
+        let text = "pos:\t0\nflags:\t02100002\nmnt_id:\t27\nino:\t1322\ndrm-driver:\txe\n\
+            drm-client-id:\t3\ndrm-pdev:\t0000:03:00.0\ndrm-total-system:\t0\n\
+            drm-cycles-rcs:\t28257900\ndrm-total-cycles-rcs:\t7655183225\n\
+            drm-cycles-bcs:\t0\ndrm-total-cycles-bcs:\t7655183225\ndrm-engine-capacity-vcs:\t2\n";
+        let client = parse_fdinfo(text).unwrap();
+
+        assert_eq!((client.pdev.as_str(), client.id), ("0000:03:00.0", 3));
+        assert_eq!(client.engines["rcs"].busy, 28257900);
+        assert_eq!(client.engines["rcs"].total_cycles, Some(7655183225));
+        assert_eq!(client.engines["bcs"].busy, 0);
+        assert!(!client.engines.contains_key("vcs"));
+    }
+
+    #[test]
+    fn ignores_non_drm_fdinfo() {
+        assert!(parse_fdinfo("pos:\t0\nflags:\t02000002\nmnt_id:\t15\nino:\t6\n").is_none());
+    }
+
+    #[test]
+    fn utilisation_only_counts_clients_in_both_samples() {
+        let sys = sys_tree();
+        let proc = TempTree::new();
+        proc.symlink("100/fd/5", "/dev/dri/renderD128")
+            .write("100/fdinfo/5", &fdinfo("0000:03:00.0", 1, 1_000, 10_000))
+            .symlink("200/fd/5", "/dev/dri/renderD128")
+            .write("200/fdinfo/5", &fdinfo("0000:03:00.0", 2, 900_000, 10_000))
+            .symlink("300/fd/4", "/dev/dri/renderD129")
+            .write("300/fdinfo/4", &fdinfo("0000:04:00.0", 3, 0, 10_000));
+
+        let backend = IntelBackend::new(sys.path(), proc.path());
+        assert_eq!(backend.device_count().unwrap(), 2);
+        let (mut card0, mut card1) = (backend.device(0).unwrap(), backend.device(1).unwrap());
+        assert_eq!(card0.name(), "Intel Graphics (xe, 0000:03:00.0)");
+        // The first sample only records the baseline
+        assert_eq!(card0.sample().utilization, None);
+        assert_eq!(card1.sample().utilization, None);
+
+        // Client 2 exits, and client 4 turns up with cycles it spent before the first sample
+        fs::remove_dir_all(proc.path().join("200")).unwrap();
+        proc.write("100/fdinfo/5", &fdinfo("0000:03:00.0", 1, 3_500, 20_000))
+            .symlink("400/fd/7", "/dev/dri/renderD128")
+            .write("400/fdinfo/7", &fdinfo("0000:03:00.0", 4, 800_000, 20_000))
+            .write("300/fdinfo/4", &fdinfo("0000:04:00.0", 3, 5_000, 20_000));
+        assert_eq!(card0.sample().utilization, Some(25.0));
+        assert_eq!(card1.sample().utilization, Some(50.0));
+
+        // Client 4 counts from the sample after it was first seen
+        proc.write("100/fdinfo/5", &fdinfo("0000:03:00.0", 1, 3_500, 30_000))
+            .write("400/fdinfo/7", &fdinfo("0000:03:00.0", 4, 801_000, 30_000));
+        assert_eq!(card0.sample().utilization, Some(10.0));
+    }
+}
@@ -0,0 +1,449 @@
+mod amd;
+mod fake;
+mod intel;
+mod nvidia;
+
+use std::path::Path;
+use druid::{Data, Lens};
+use im::Vector;
+use sysinfo::{Pid, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind};
+use crate::history::{percent, TimeSeries};
+use crate::processes::UserNames;
+use crate::sampler::{MetricSource, SeriesInfo, Snapshot, SourceError};
+use crate::settings::Settings;
+
+pub (crate) use amd::AmdBackend;
+pub (crate) use fake::FakeBackend;
+pub (crate) use intel::IntelBackend;
+pub (crate) use nvidia::NvmlBackend;
+
+#[derive(Clone, Debug)]
+pub (crate) struct GPU {
+    pub(crate) index: usize,
+    pub(crate)brand: String,
+    pub(crate)name: String,
+    pub(crate)temp_history: TimeSeries,
+    pub(crate)fan_speed_history: Vector<TimeSeries>,
+    // SM and memory-controller utilisation in percent
+    pub(crate)util_history: TimeSeries,
+    pub(crate) mem_util_history: TimeSeries,
+    // Board power and its enforced limit in watts, for backends that report them
+    pub(crate) power_history: TimeSeries,
+    pub(crate) power_limit: f64,
+    // Graphics and memory clocks in MHz, for backends that report them
+    pub(crate) clock_history: TimeSeries,
+    pub(crate) mem_clock_history: TimeSeries,
+    // PCIe throughput in bytes per second
+    pub(crate) pcie_rx_history: TimeSeries,
+    pub(crate) pcie_tx_history: TimeSeries,
+    // VRAM usage as a percentage of total
+    pub(crate) used_mem_history: TimeSeries,
+    pub(crate)used_mem: f64,
+    pub(crate)total_mem: f64,
+    pub(crate) processes: Vector<GpuProcessInfo>,
+}
+
+// A process using a GPU, joined with its name and owner from the process table
+#[derive(Clone, Data, Lens, Debug)]
+pub (crate) struct GpuProcessInfo {
+    pub(crate) gpu: usize,
+    pub(crate) pid: u32,
+    pub(crate) name: String,
+    pub(crate) user: String,
+    // VRAM in bytes and SM utilisation in percent; None when the driver does not report them
+    pub(crate) used_mem: Option<f64>,
+    pub(crate) sm_util: Option<f64>,
+}
+
+pub (crate) const MAX_RPM:u32 = 3000;
+
+pub (crate) type GpuError = Box<dyn std::error::Error + Send + Sync>;
+
+// A single reading from a device; fields are None when the device does not report them
+#[derive(Clone, Debug, Default)]
+pub (crate) struct GpuSample {
+    pub(crate) temperature: Option<f64>,
+    pub(crate) fan_speeds: Vec<Option<f64>>,
+    pub(crate) utilization: Option<f64>,
+    pub(crate) memory_utilization: Option<f64>,
+    pub(crate) memory: Option<MemoryUsage>,
+    pub(crate) power: Option<f64>,
+    pub(crate) power_limit: Option<f64>,
+    pub(crate) graphics_clock: Option<f64>,
+    pub(crate) memory_clock: Option<f64>,
+    pub(crate) pcie_rx: Option<f64>,
+    pub(crate) pcie_tx: Option<f64>,
+    pub(crate) processes: Vec<GpuProcess>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub (crate) struct GpuProcess {
+    pub(crate) pid: u32,
+    pub(crate) used_mem: Option<u64>,
+    pub(crate) sm_util: Option<f64>,
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub (crate) struct MemoryUsage {
+    pub(crate) used: u64,
+    pub(crate) total: u64,
+}
+
+// Entry point for a GPU vendor API (NVML, amdgpu/i915 sysfs, the fake device, ...)
+pub (crate) trait GpuBackend: Send {
+    fn device_count(&self) -> Result<u32, GpuError>;
+    fn device(&self, index: u32) -> Result<Box<dyn GpuDevice>, GpuError>;
+}
+
+// A single card as seen by the sampler thread
+pub (crate) trait GpuDevice: Send {
+    fn brand(&self) -> String;
+    fn name(&self) -> String;
+    fn num_fans(&self) -> u32;
+    fn sample(&mut self) -> GpuSample;
+}
+
+// Unsupported readings leave the history untouched
+fn push_value(history: &mut TimeSeries, value: Option<f64>) {
+    if let Some(value) = value {
+        history.push(value);
+    }
+}
+
+impl GPU {
+    pub (crate) fn new(index: usize, brand: String, name: String, num_fans: u32, history_size: usize) -> Self {
+        GPU {
+            index,
+            brand,
+            name,
+            temp_history: TimeSeries::new(history_size),
+            fan_speed_history: (0..num_fans).map(|_| TimeSeries::new(history_size)).collect(),
+            util_history: TimeSeries::new(history_size),
+            mem_util_history: TimeSeries::new(history_size),
+            power_history: TimeSeries::new(history_size),
+            power_limit: 0.0,
+            clock_history: TimeSeries::new(history_size),
+            mem_clock_history: TimeSeries::new(history_size),
+            pcie_rx_history: TimeSeries::new(history_size),
+            pcie_tx_history: TimeSeries::new(history_size),
+            used_mem_history: TimeSeries::new(history_size),
+            used_mem: 0.0,
+            total_mem: 0.0,
+            processes: Vector::new(),
+        }
+    }
+
+    pub (crate) fn set_capacity(&mut self, capacity: usize) {
+        for history in [
+            &mut self.temp_history,
+            &mut self.util_history,
+            &mut self.mem_util_history,
+            &mut self.power_history,
+            &mut self.clock_history,
+            &mut self.mem_clock_history,
+            &mut self.pcie_rx_history,
+            &mut self.pcie_tx_history,
+            &mut self.used_mem_history,
+        ] {
+            history.set_capacity(capacity);
+        }
+        for history in self.fan_speed_history.iter_mut() {
+            history.set_capacity(capacity);
+        }
+    }
+
+    pub (crate) fn push(&mut self, sample: &GpuSample) {
+        push_value(&mut self.temp_history, sample.temperature);
+        for (history, speed) in self.fan_speed_history.iter_mut().zip(sample.fan_speeds.iter()) {
+            push_value(history, *speed);
+        }
+        push_value(&mut self.util_history, sample.utilization);
+        push_value(&mut self.mem_util_history, sample.memory_utilization);
+        push_value(&mut self.power_history, sample.power);
+        if let Some(limit) = sample.power_limit {
+            self.power_limit = limit;
+        }
+        push_value(&mut self.clock_history, sample.graphics_clock);
+        push_value(&mut self.mem_clock_history, sample.memory_clock);
+        push_value(&mut self.pcie_rx_history, sample.pcie_rx);
+        push_value(&mut self.pcie_tx_history, sample.pcie_tx);
+
+        // Update memory info, storing VRAM usage as a percentage of total (0..100)
+        if let Some(memory) = sample.memory {
+            self.used_mem_history.push(percent(memory.used as f64, memory.total as f64));
+            self.used_mem = memory.used as f64;
+            self.total_mem = memory.total as f64;
+        }
+    }
+}
+
+// Resolves the PIDs reported by the GPUs to process names and owners
+struct ProcessTable {
+    sys: System,
+    users: UserNames,
+}
+
+impl ProcessTable {
+    fn new() -> Self {
+        Self { sys: System::new(), users: UserNames::new() }
+    }
+
+    fn resolve(&mut self, gpu: usize, processes: &[GpuProcess]) -> Vector<GpuProcessInfo> {
+        let pids: Vec<Pid> = processes.iter().map(|p| Pid::from_u32(p.pid)).collect();
+        self.users.start_sample();
+        self.sys.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&pids),
+            true,
+            ProcessRefreshKind::nothing().with_user(UpdateKind::OnlyIfNotSet),
+        );
+
+        processes.iter()
+            .map(|p| {
+                let process = self.sys.process(Pid::from_u32(p.pid));
+                let name = process
+                    .map(|proc| proc.name().to_string_lossy().into_owned())
+                    .unwrap_or_else(|| "?".to_string());
+                let user = self.users.name(process.and_then(|proc| proc.user_id()));
+
+                GpuProcessInfo {
+                    gpu,
+                    pid: p.pid,
+                    name,
+                    user,
+                    used_mem: p.used_mem.map(|bytes| bytes as f64),
+                    sm_util: p.sm_util,
+                }
+            })
+            .collect()
+    }
+}
+
+// Every GPU the backends can see, sampled as a single source
+pub (crate) struct GpuSource {
+    backends: Vec<Box<dyn GpuBackend>>,
+    devices: Vec<Box<dyn GpuDevice>>,
+    process_table: ProcessTable,
+}
+
+impl GpuSource {
+    pub (crate) fn new(fake: bool, sys_root: impl AsRef<Path>, proc_root: impl AsRef<Path>) -> Self {
+        let mut backends: Vec<Box<dyn GpuBackend>> = Vec::new();
+        if fake {
+            backends.push(Box::new(FakeBackend::demo()));
+        } else {
+            // NVML is missing on machines without the NVIDIA driver; carry on with the other vendors
+            let mut nvidia_found = false;
+            match NvmlBackend::init() {
+                Ok(backend) => {
+                    nvidia_found = backend.device_count().unwrap_or(0) > 0;
+                    backends.push(Box::new(backend));
+                }
+                Err(err) => eprintln!("NVML unavailable, NVIDIA GPU monitoring disabled: {:?}", err),
+            }
+            backends.push(Box::new(AmdBackend::new(&sys_root)));
+            // Integrated Intel graphics are only worth showing when there is no NVIDIA card
+            if !nvidia_found {
+                backends.push(Box::new(IntelBackend::new(&sys_root, &proc_root)));
+            }
+        }
+
+        Self { backends, devices: Vec::new(), process_table: ProcessTable::new() }
+    }
+}
+
+impl MetricSource for GpuSource {
+    fn name(&self) -> &'static str {
+        "gpu"
+    }
+
+    fn init(&mut self, snapshot: &mut Snapshot, settings: &Settings) -> Result<(), SourceError> {
+        // Probe the devices up front so a missing GPU disables the source rather than failing every tick
+        for backend in self.backends.drain(..) {
+            let count = match backend.device_count() {
+                Ok(count) => count,
+                Err(err) => {
+                    eprintln!("Failed to enumerate GPUs: {:?}", err);
+                    continue;
+                }
+            };
+            for index in 0..count {
+                match backend.device(index) {
+                    Ok(device) => {
+                        snapshot.gpus.push_back(GPU::new(
+                            self.devices.len(),
+                            device.brand(),
+                            device.name(),
+                            device.num_fans(),
+                            settings.history_size(),
+                        ));
+                        self.devices.push(device);
+                    }
+                    Err(err) => eprintln!("Failed to open GPU {}: {:?}", index, err),
+                }
+            }
+        }
+        if self.devices.is_empty() {
+            return Err("no GPU found".into());
+        }
+        Ok(())
+    }
+
+    fn sample(&mut self, snapshot: &mut Snapshot, settings: &Settings) -> Result<(), SourceError> {
+        for (device, gpu) in self.devices.iter_mut().zip(snapshot.gpus.iter_mut()) {
+            gpu.set_capacity(settings.history_size());
+            let sample = device.sample();
+            gpu.push(&sample);
+            gpu.processes = self.process_table.resolve(gpu.index, &sample.processes);
+        }
+        Ok(())
+    }
+
+    fn series(&self) -> Vec<SeriesInfo> {
+        let mut series = Vec::new();
+        for (index, device) in self.devices.iter().enumerate() {
+            let gpu = |name: &str, unit| SeriesInfo::new(format!("gpu{}.{}", index, name), unit);
+            series.push(gpu("temperature", "°C"));
+            series.extend((0..device.num_fans()).map(|fan| gpu(&format!("fan{}", fan + 1), "RPM")));
+            series.push(gpu("utilization.sm", "%"));
+            series.push(gpu("utilization.memory", "%"));
+            series.push(gpu("power", "W"));
+            series.push(gpu("clock.graphics", "MHz"));
+            series.push(gpu("clock.memory", "MHz"));
+            series.push(gpu("pcie.rx", "B/s"));
+            series.push(gpu("pcie.tx", "B/s"));
+            series.push(gpu("vram.used", "%"));
+        }
+        series
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gpu::fake::FakeDevice;
+    use crate::ui::usage_graph::PlotType;
+
+    // Feed `ticks` samples from `device` into a GPU built the way GpuSource::init builds one
+    fn run(mut device: FakeDevice, ticks: usize, history_size: usize) -> GPU {
+        let mut gpu = GPU::new(0, device.brand(), device.name(), device.num_fans(), history_size);
+        for _ in 0..ticks {
+            gpu.push(&device.sample());
+        }
+        gpu
+    }
+
+    fn values(history: &TimeSeries) -> Vec<f64> {
+        history.iter().collect()
+    }
+
+    #[test]
+    fn push_records_scripted_readings() {
+        let device = FakeDevice::new("test")
+            .temperatures(vec![40.0, 50.0])
+            .fan_speeds(vec![vec![1000.0], vec![1200.0, 1300.0]])
+            .utilization(vec![10.0, 20.0, 30.0], vec![5.0])
+            .power(vec![100.0], 250.0)
+            .pcie(vec![1e9], vec![2e9]);
+        let gpu = run(device, 3, 10);
+
+        assert_eq!(values(&gpu.temp_history), [40.0, 50.0, 40.0]);
+        assert_eq!(gpu.fan_speed_history.len(), 2);
+        assert_eq!(values(&gpu.fan_speed_history[0]), [1000.0, 1000.0, 1000.0]);
+        assert_eq!(values(&gpu.fan_speed_history[1]), [1200.0, 1300.0, 1200.0]);
+        assert_eq!(values(&gpu.util_history), [10.0, 20.0, 30.0]);
+        assert_eq!(values(&gpu.mem_util_history), [5.0, 5.0, 5.0]);
+        assert_eq!(gpu.power_limit, 250.0);
+        assert_eq!(values(&gpu.pcie_tx_history), [2e9, 2e9, 2e9]);
+    }
+
+    #[test]
+    fn vram_is_stored_as_clamped_percent() {
+        let device = FakeDevice::new("test").memory(vec![256, 1024, 2048], 1024);
+        let gpu = run(device, 3, 10);
+
+        assert_eq!(values(&gpu.used_mem_history), [25.0, 100.0, 100.0]);
+        assert_eq!(gpu.used_mem, 2048.0);
+        assert_eq!(gpu.total_mem, 1024.0);
+
+        // A device reporting no total can't be scaled, so it reads as empty rather than NaN
+        let gpu = run(FakeDevice::new("test").memory(vec![512], 0), 1, 10);
+        assert_eq!(values(&gpu.used_mem_history), [0.0]);
+    }
+
+    #[test]
+    fn unsupported_readings_leave_history_unchanged() {
+        let gpu = run(FakeDevice::new("test").temperatures(vec![60.0]), 4, 10);
+
+        assert_eq!(gpu.temp_history.len(), 4);
+        for history in [
+            &gpu.util_history,
+            &gpu.mem_util_history,
+            &gpu.power_history,
+            &gpu.clock_history,
+            &gpu.mem_clock_history,
+            &gpu.pcie_rx_history,
+            &gpu.pcie_tx_history,
+            &gpu.used_mem_history,
+        ] {
+            assert!(history.is_empty());
+        }
+        assert!(gpu.fan_speed_history.is_empty());
+        assert_eq!(gpu.power_limit, 0.0);
+        assert_eq!(gpu.total_mem, 0.0);
+    }
+
+    #[test]
+    fn set_capacity_trims_history() {
+        let device = FakeDevice::new("test")
+            .temperatures((0..10).map(f64::from).collect())
+            .fan_speeds(vec![vec![1000.0]])
+            .memory(vec![512], 1024);
+        let mut gpu = run(device, 10, 10);
+        gpu.set_capacity(3);
+
+        assert_eq!(values(&gpu.temp_history), [7.0, 8.0, 9.0]);
+        assert_eq!(gpu.fan_speed_history[0].len(), 3);
+        assert_eq!(gpu.used_mem_history.len(), 3);
+        assert_eq!(gpu.temp_history.capacity(), 3);
+    }
+
+    #[test]
+    fn fan_speeds_are_scaled_to_max_rpm() {
+        let gpu = run(FakeDevice::new("test").fan_speeds(vec![vec![1500.0, 6000.0]]), 2, 10);
+        let axis_max = PlotType::GPUFan.gpu_axis_max(&[&gpu]);
+        let series = PlotType::GPUFan.gpu_series(&gpu, axis_max);
+
+        assert_eq!(axis_max, MAX_RPM as f64);
+        assert_eq!(series.len(), 1);
+        assert_eq!(series[0].0, "Fan 1");
+        assert_eq!(values(&series[0].1), [50.0, 100.0]);
+    }
+
+    #[test]
+    fn power_is_scaled_to_the_largest_limit() {
+        let low = run(FakeDevice::new("low").power(vec![75.0], 150.0), 1, 10);
+        let high = run(FakeDevice::new("high").power(vec![150.0], 300.0), 1, 10);
+        let axis_max = PlotType::GPUPower.gpu_axis_max(&[&low, &high]);
+
+        assert_eq!(axis_max, 300.0);
+        assert_eq!(values(&PlotType::GPUPower.gpu_series(&low, axis_max)[0].1), [25.0]);
+        assert_eq!(values(&PlotType::GPUPower.gpu_series(&high, axis_max)[0].1), [50.0]);
+
+        // Without a reported limit the axis rounds the peak draw up
+        let unlimited = run(FakeDevice::new("test").power(vec![130.0], 0.0), 1, 10);
+        assert_eq!(PlotType::GPUPower.gpu_axis_max(&[&unlimited]), 200.0);
+    }
+
+    #[test]
+    fn pcie_is_scaled_to_the_rounded_peak() {
+        let gpu = run(FakeDevice::new("test").pcie(vec![1e9, 3e9], vec![2e9]), 2, 10);
+        let axis_max = PlotType::GPUPcie.gpu_axis_max(&[&gpu]);
+        let series = PlotType::GPUPcie.gpu_series(&gpu, axis_max);
+
+        assert_eq!(axis_max, 5e9);
+        assert_eq!(series[0].0, "RX");
+        assert_eq!(values(&series[0].1), [20.0, 60.0]);
+        assert_eq!(series[1].0, "TX");
+        assert_eq!(values(&series[1].1), [40.0, 40.0]);
+    }
+}
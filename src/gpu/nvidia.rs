@@ -0,0 +1,147 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use nvml_wrapper::enum_wrappers::device::{Clock, PcieUtilCounter, TemperatureSensor};
+use nvml_wrapper::enums::device::UsedGpuMemory;
+use nvml_wrapper::error::NvmlError;
+use nvml_wrapper::{Device, Nvml};
+use crate::gpu::{GpuBackend, GpuDevice, GpuError, GpuProcess, GpuSample, MemoryUsage};
+
+pub (crate) struct NvmlBackend {
+    nvml: Arc<Nvml>,
+}
+
+impl NvmlBackend {
+    pub (crate) fn init() -> Result<Self, NvmlError> {
+        Ok(Self { nvml: Arc::new(Nvml::init()?) })
+    }
+}
+
+impl GpuBackend for NvmlBackend {
+    fn device_count(&self) -> Result<u32, GpuError> {
+        Ok(self.nvml.device_count()?)
+    }
+
+    fn device(&self, index: u32) -> Result<Box<dyn GpuDevice>, GpuError> {
+        let device = self.nvml.device_by_index(index)?;
+        let brand = device
+            .brand()
+            .map(|b| format!("{:?}", b))
+            .unwrap_or_else(|_| "Unknown".to_string());
+        let name = device
+            .name()
+            .unwrap_or_else(|_| "Unknown".to_string());
+        // Passively cooled and laptop GPUs report no fans; keep sampling the other metrics
+        let num_fans = match device.num_fans() {
+            Ok(n) => n,
+            Err(e) => {
+                eprintln!("Failed to query number of fans: {:?}", e);
+                0
+            }
+        };
+
+        Ok(Box::new(NvmlDevice {
+            nvml: self.nvml.clone(),
+            index,
+            brand,
+            name,
+            num_fans,
+            last_process_sample: None,
+            sm_utils: HashMap::new(),
+        }))
+    }
+}
+
+// NVML devices borrow the Nvml handle, so keep the Arc and look the device up on each sample
+struct NvmlDevice {
+    nvml: Arc<Nvml>,
+    index: u32,
+    brand: String,
+    name: String,
+    num_fans: u32,
+    // Timestamp of the newest per-process utilisation sample already seen
+    last_process_sample: Option<u64>,
+    // Latest SM usage of each running process. The driver samples less often than we poll, so
+    // a value is carried forward until a newer sample arrives.
+    sm_utils: HashMap<u32, f64>,
+}
+
+impl NvmlDevice {
+    // Compute and graphics clients with their VRAM, plus SM usage from the driver's utilisation samples
+    fn processes(&mut self, device: &Device) -> Vec<GpuProcess> {
+        let mut processes: HashMap<u32, GpuProcess> = HashMap::new();
+        let running = device.running_compute_processes().unwrap_or_default()
+            .into_iter()
+            .chain(device.running_graphics_processes().unwrap_or_default());
+        for info in running {
+            let used_mem = match info.used_gpu_memory {
+                UsedGpuMemory::Used(bytes) => Some(bytes),
+                UsedGpuMemory::Unavailable => None,
+            };
+            // A process using both compute and graphics is listed twice, possibly with its memory
+            // figure missing from one of the lists, so keep whichever one is known
+            let entry = processes.entry(info.pid)
+                .or_insert(GpuProcess { pid: info.pid, used_mem: None, sm_util: None });
+            entry.used_mem = used_mem.or(entry.used_mem);
+        }
+
+        // NotFound just means there is no sample newer than the last one seen
+        if let Ok(samples) = device.process_utilization_stats(self.last_process_sample) {
+            for sample in samples {
+                self.last_process_sample = Some(self.last_process_sample.unwrap_or(0).max(sample.timestamp));
+                self.sm_utils.insert(sample.pid, sample.sm_util as f64);
+            }
+        }
+        self.sm_utils.retain(|pid, _| processes.contains_key(pid));
+        for (pid, process) in processes.iter_mut() {
+            process.sm_util = self.sm_utils.get(pid).copied();
+        }
+
+        processes.into_values().collect()
+    }
+}
+
+impl GpuDevice for NvmlDevice {
+    fn brand(&self) -> String {
+        self.brand.clone()
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+
+    fn num_fans(&self) -> u32 {
+        self.num_fans
+    }
+
+    fn sample(&mut self) -> GpuSample {
+        // Look the device up through a local handle so `self` stays free for the process bookkeeping
+        let nvml = self.nvml.clone();
+        let device = match nvml.device_by_index(self.index) {
+            Ok(d) => d,
+            Err(e) => {
+                eprintln!("Failed to get NVML device: {:?}", e);
+                return GpuSample::default();
+            }
+        };
+
+        let utilization = device.utilization_rates().ok();
+        let processes = self.processes(&device);
+        // NVML reports power in milliwatts and PCIe throughput in KB/s
+        GpuSample {
+            temperature: device.temperature(TemperatureSensor::Gpu).ok().map(|t| t as f64),
+            fan_speeds: (0..self.num_fans)
+                .map(|i| device.fan_speed(i).ok().map(|s| s as f64))
+                .collect(),
+            utilization: utilization.as_ref().map(|u| u.gpu as f64),
+            memory_utilization: utilization.as_ref().map(|u| u.memory as f64),
+            memory: device.memory_info().ok().map(|m| MemoryUsage { used: m.used, total: m.total }),
+            power: device.power_usage().ok().map(|mw| mw as f64 / 1000.0),
+            power_limit: device.enforced_power_limit().ok().map(|mw| mw as f64 / 1000.0),
+            graphics_clock: device.clock_info(Clock::Graphics).ok().map(|mhz| mhz as f64),
+            memory_clock: device.clock_info(Clock::Memory).ok().map(|mhz| mhz as f64),
+            pcie_rx: device.pcie_throughput(PcieUtilCounter::Receive).ok().map(|kb| kb as f64 * 1024.0),
+            pcie_tx: device.pcie_throughput(PcieUtilCounter::Send).ok().map(|kb| kb as f64 * 1024.0),
+            processes,
+        }
+    }
+}
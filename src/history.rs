@@ -0,0 +1,201 @@
+use std::time::{Duration, Instant};
+use im::Vector;
+use crate::settings::DEFAULT_HISTORY_SIZE;
+
+// Scale `value` to a 0..100 percentage of `max`, clamping anything out of range
+pub (crate) fn percent(value: f64, max: f64) -> f64 {
+    let pct = if max > 0.0 { (value / max) * 100.0 } else { 0.0 };
+    if pct.is_finite() { pct.clamp(0.0, 100.0) } else { 0.0 }
+}
+
+// Fixed-capacity series of timestamped samples, oldest first. Backed by im::Vector, so pushing
+// drops the oldest sample in O(1) and cloning a snapshot for the UI shares the sampler's storage
+// instead of copying it.
+#[derive(Clone, Debug)]
+pub (crate) struct TimeSeries {
+    capacity: usize,
+    values: Vector<f64>,
+    timestamps: Vector<Instant>,
+}
+
+impl Default for TimeSeries {
+    fn default() -> Self {
+        Self::new(DEFAULT_HISTORY_SIZE)
+    }
+}
+
+impl TimeSeries {
+    pub (crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            values: Vector::new(),
+            timestamps: Vector::new(),
+        }
+    }
+
+    pub (crate) fn push(&mut self, value: f64) {
+        self.push_at(Instant::now(), value);
+    }
+
+    pub (crate) fn push_at(&mut self, timestamp: Instant, value: f64) {
+        self.values.push_back(value);
+        self.timestamps.push_back(timestamp);
+        self.trim();
+    }
+
+    // Shrinking drops the oldest samples; growing keeps everything and makes room for more
+    pub (crate) fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity.max(1);
+        self.trim();
+    }
+
+    fn trim(&mut self) {
+        while self.values.len() > self.capacity {
+            self.values.pop_front();
+            self.timestamps.pop_front();
+        }
+    }
+
+    pub (crate) fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub (crate) fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub (crate) fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    pub (crate) fn latest(&self) -> Option<f64> {
+        self.values.last().copied()
+    }
+
+    // For labels, which show an empty history as zero
+    pub (crate) fn latest_or_zero(&self) -> f64 {
+        self.latest().unwrap_or(0.0)
+    }
+
+    pub (crate) fn iter(&self) -> impl Iterator<Item = f64> + '_ {
+        self.values.iter().copied()
+    }
+
+    // Time covered by the samples currently held
+    pub (crate) fn span(&self) -> Duration {
+        match (self.timestamps.front(), self.timestamps.back()) {
+            (Some(first), Some(last)) => last.duration_since(*first),
+            _ => Duration::ZERO,
+        }
+    }
+
+    pub (crate) fn max(&self) -> f64 {
+        self.iter().fold(0.0, f64::max)
+    }
+
+    // A copy with every value passed through `f`, keeping timestamps and capacity
+    pub (crate) fn map(&self, f: impl Fn(f64) -> f64) -> TimeSeries {
+        TimeSeries {
+            capacity: self.capacity,
+            values: self.values.iter().map(|v| f(*v)).collect(),
+            timestamps: self.timestamps.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values(series: &TimeSeries) -> Vec<f64> {
+        series.iter().collect()
+    }
+
+    #[test]
+    fn push_drops_the_oldest_samples() {
+        let mut series = TimeSeries::new(3);
+        assert_eq!(series.latest(), None);
+        assert_eq!(series.latest_or_zero(), 0.0);
+
+        for value in [1.0, 2.0, 3.0, 4.0, 5.0] {
+            series.push(value);
+        }
+        assert_eq!(values(&series), [3.0, 4.0, 5.0]);
+        assert_eq!(series.latest(), Some(5.0));
+        assert_eq!(series.max(), 5.0);
+    }
+
+    #[test]
+    fn set_capacity_shrinks_and_grows() {
+        let mut series = TimeSeries::new(5);
+        for value in [1.0, 2.0, 3.0, 4.0, 5.0] {
+            series.push(value);
+        }
+        series.set_capacity(2);
+        assert_eq!(values(&series), [4.0, 5.0]);
+
+        // Growing keeps what is left and lets the series fill up again
+        series.set_capacity(4);
+        series.push(6.0);
+        series.push(7.0);
+        series.push(8.0);
+        assert_eq!(values(&series), [5.0, 6.0, 7.0, 8.0]);
+        assert_eq!(series.capacity(), 4);
+    }
+
+    #[test]
+    fn capacity_is_at_least_one() {
+        let mut series = TimeSeries::new(0);
+        assert_eq!(series.capacity(), 1);
+        series.push(1.0);
+        series.push(2.0);
+        assert_eq!(values(&series), [2.0]);
+
+        series.set_capacity(0);
+        assert_eq!(series.capacity(), 1);
+        assert_eq!(values(&series), [2.0]);
+    }
+
+    #[test]
+    fn span_covers_the_held_samples() {
+        let start = Instant::now();
+        let mut series = TimeSeries::new(3);
+        assert_eq!(series.span(), Duration::ZERO);
+
+        series.push_at(start, 1.0);
+        assert_eq!(series.span(), Duration::ZERO);
+        for seconds in 1..=3 {
+            series.push_at(start + Duration::from_secs(seconds), 1.0);
+        }
+        // The sample at `start` has been dropped
+        assert_eq!(series.span(), Duration::from_secs(2));
+    }
+
+    #[test]
+    fn map_keeps_timestamps_and_capacity() {
+        let start = Instant::now();
+        let mut series = TimeSeries::new(4);
+        series.push_at(start, 1.0);
+        series.push_at(start + Duration::from_secs(5), 3.0);
+
+        let doubled = series.map(|value| value * 2.0);
+        assert_eq!(values(&doubled), [2.0, 6.0]);
+        assert_eq!(doubled.span(), Duration::from_secs(5));
+        assert_eq!(doubled.capacity(), 4);
+        assert_eq!(values(&series), [1.0, 3.0]);
+    }
+
+    #[test]
+    fn percent_is_clamped_and_finite() {
+        assert_eq!(percent(25.0, 200.0), 12.5);
+        assert_eq!(percent(300.0, 200.0), 100.0);
+        assert_eq!(percent(-5.0, 200.0), 0.0);
+        // Nothing to scale against, e.g. a device that reports no total
+        assert_eq!(percent(25.0, 0.0), 0.0);
+        assert_eq!(percent(25.0, -1.0), 0.0);
+        assert_eq!(percent(25.0, f64::NAN), 0.0);
+        assert_eq!(percent(25.0, f64::INFINITY), 0.0);
+        assert_eq!(percent(f64::INFINITY, 200.0), 0.0);
+        assert_eq!(percent(f64::NAN, 200.0), 0.0);
+    }
+}
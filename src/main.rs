@@ -1,16 +1,92 @@
 mod ui;
+mod cgroups;
+mod diskio;
+mod filesystems;
 mod gpu;
+mod history;
+mod network;
+mod pressure;
+mod processes;
+mod sampler;
+mod sensors;
+mod settings;
 mod system;
+mod sysfs;
 
+use std::collections::HashSet;
 use std::io::{Error, ErrorKind};
-use druid::{AppLauncher, Data, Lens, LocalizedString, WindowDesc, LensExt, Selector, RenderContext};
-use crate::gpu::GPU;
-use crate::system::SystemStats;
+use std::sync::Arc;
+use druid::{AppDelegate, AppLauncher, Command, Data, DelegateCtx, Env, Handled, Lens, LocalizedString, WindowDesc, LensExt, Selector, RenderContext};
+use im::Vector;
+use crate::cgroups::{effective_limits, own_cgroup, Cgroup, CgroupFocus, CgroupSource, Limits};
+use crate::diskio::{BlockDevice, DiskIoSource};
+use crate::filesystems::{Filesystem, FilesystemSource};
+use crate::gpu::{GpuProcessInfo, GpuSource, GPU};
+use crate::network::{NetworkInterface, NetworkSource};
+use crate::pressure::{Pressure, PressureSource, Resource};
+use crate::processes::{ProcessControl, ProcessInfo, ProcessSource};
+use crate::sampler::{Scheduler, Snapshot};
+use crate::sensors::{Sensor, SensorKind, SensorSource};
+use crate::settings::{Options, Settings};
+use crate::system::{SystemSource, SystemStats};
+use crate::ui::cgroups::{CgroupColumn, CgroupRow};
+use crate::ui::filesystems::{FilesystemColumn, FilesystemRow};
+use crate::ui::gpu_processes::GpuProcessColumn;
+use crate::ui::processes::{ProcessColumn, ProcessRow};
+use crate::ui::sensors::SensorRow;
+use crate::ui::table::TableSort;
 
 #[derive(Clone, Lens, Debug)]
 struct State {
     system: SystemStats,
-    gpu: GPU,
+    // One entry per detected GPU, empty when no supported GPU was found at startup
+    gpus: Vector<GPU>,
+    // GPU shown in the GPU graphs, or None to compare all of them
+    gpu_view: Option<usize>,
+    gpu_process_sort: TableSort<GpuProcessColumn>,
+    processes: Vector<ProcessInfo>,
+    process_sort: TableSort<ProcessColumn>,
+    // Tree view of the process table, with the processes whose children are hidden
+    process_tree: bool,
+    collapsed_processes: HashSet<u32>,
+    // Process the actions above the process table apply to
+    selected_pid: Option<u32>,
+    nice: f64,
+    affinity: String,
+    // Outcome of the last process action, including permission errors
+    process_status: String,
+    process_control: ProcessControl,
+    network: Vector<NetworkInterface>,
+    // Network interface filters
+    show_loopback: bool,
+    show_virtual_interfaces: bool,
+    disks: Vector<BlockDevice>,
+    filesystems: Vector<Filesystem>,
+    filesystem_sort: TableSort<FilesystemColumn>,
+    // Used space or inodes, in percent, above which a filesystem is highlighted
+    filesystem_threshold: f64,
+    sensors: Vector<Sensor>,
+    // Ids of the sensors drawn in the sensor graphs
+    charted_sensors: HashSet<String>,
+    // Pressure stall information per resource, empty when the kernel doesn't provide it
+    pressure: Vector<Pressure>,
+    cgroups: Vector<Cgroup>,
+    cgroup_sort: TableSort<CgroupColumn>,
+    // Groups whose children are listed, and the group shown in the cgroup graphs
+    expanded_cgroups: HashSet<String>,
+    selected_cgroup: Option<String>,
+    // Show CPU and RAM relative to the limits of this group rather than the whole host
+    limits_cgroup: Option<String>,
+    use_limits: bool,
+    // Table rows in display order, rebuilt when the snapshot, a sort order or an expanded/selected
+    // row changes rather than on every widget pass
+    gpu_process_rows: Vector<GpuProcessInfo>,
+    process_rows: Vector<ProcessRow>,
+    filesystem_rows: Vector<FilesystemRow>,
+    sensor_rows: Vector<SensorRow>,
+    cgroup_rows: Vector<CgroupRow>,
+    // Sample interval and history length, shared with the sampler thread
+    settings: Arc<Settings>,
 }
 
 
@@ -21,23 +97,211 @@ impl Data for State {
     }
 }
 
-const HISTORY_SIZE: usize = 120; // number of samples per core
-const UPDATE_METRICS: Selector<SystemStats> = Selector::new("update_metrics");
-const UPDATE_GPU: Selector<GPU> = Selector::new("update_gpu");
+impl State {
+    fn update_tables(&mut self) {
+        ui::gpu_processes::update_rows(self);
+        ui::processes::update_rows(self);
+        ui::filesystems::update_rows(self);
+        ui::sensors::update_rows(self);
+        ui::cgroups::update_rows(self);
+    }
+
+    // GPUs currently shown in the GPU graphs
+    fn visible_gpus(&self) -> Vec<&GPU> {
+        match self.gpu_view {
+            Some(index) => self.gpus.iter().filter(|gpu| gpu.index == index).collect(),
+            None => self.gpus.iter().collect(),
+        }
+    }
+
+    // Sensors of one kind ticked for charting
+    fn sensors_to_chart(&self, kind: SensorKind) -> Vec<&Sensor> {
+        self.sensors.iter()
+            .filter(|sensor| sensor.kind == kind && self.charted_sensors.contains(&sensor.id))
+            .collect()
+    }
+
+    fn resource_pressure(&self, resource: Resource) -> Option<&Pressure> {
+        self.pressure.iter().find(|pressure| pressure.resource == resource)
+    }
+
+    // Group shown in the cgroup graphs, while it still exists
+    fn current_cgroup(&self) -> Option<&Cgroup> {
+        let path = self.selected_cgroup.as_ref()?;
+        self.cgroups.iter().find(|group| &group.path == path)
+    }
+
+    // The group CPU and RAM are shown against, with its effective limits, when limits mode is on
+    fn active_limits(&self) -> Option<(&Cgroup, Limits)> {
+        if !self.use_limits {
+            return None;
+        }
+        let path = self.limits_cgroup.as_ref()?;
+        let group = self.cgroups.iter().find(|group| &group.path == path)?;
+        Some((group, effective_limits(&self.cgroups, path)))
+    }
+
+    // Tell the cgroup source which groups the table, graphs and limits mode need
+    fn share_cgroup_focus(&self) {
+        let focus = CgroupFocus::new(&self.expanded_cgroups, self.selected_cgroup.as_ref(), self.limits_cgroup.as_ref());
+        self.settings.set_cgroup_focus(focus);
+    }
+
+    // Network interfaces left after the loopback and virtual filters
+    fn visible_interfaces(&self) -> Vec<&NetworkInterface> {
+        self.network.iter()
+            .filter(|interface| self.show_loopback || !interface.loopback)
+            .filter(|interface| self.show_virtual_interfaces || interface.loopback || !interface.is_virtual)
+            .collect()
+    }
+}
+
+const UPDATE_SNAPSHOT: Selector<Snapshot> = Selector::new("update_snapshot");
+pub (crate) const SELECT_PROCESS: Selector<u32> = Selector::new("select_process");
+pub (crate) const TOGGLE_PROCESS: Selector<u32> = Selector::new("toggle_process");
+pub (crate) const CHART_SENSOR: Selector<String> = Selector::new("chart_sensor");
+pub (crate) const SELECT_CGROUP: Selector<String> = Selector::new("select_cgroup");
+pub (crate) const TOGGLE_CGROUP: Selector<String> = Selector::new("toggle_cgroup");
+
+// Applies each snapshot from the sampler to the app state
+struct Delegate;
+
+impl AppDelegate<State> for Delegate {
+    fn command(&mut self, _ctx: &mut DelegateCtx, _target: druid::Target, cmd: &Command, data: &mut State, _env: &Env) -> Handled {
+        if let Some(snapshot) = cmd.get(UPDATE_SNAPSHOT) {
+            data.system = snapshot.system.clone();
+            data.gpus = snapshot.gpus.clone();
+            data.processes = snapshot.processes.clone();
+            data.network = snapshot.network.clone();
+            data.disks = snapshot.disks.clone();
+            data.filesystems = snapshot.filesystems.clone();
+            data.sensors = snapshot.sensors.clone();
+            data.pressure = snapshot.pressure.clone();
+            data.cgroups = snapshot.cgroups.clone();
+            data.update_tables();
+            return Handled::Yes;
+        }
+        if let Some(pid) = cmd.get(SELECT_PROCESS) {
+            data.selected_pid = Some(*pid);
+            ui::processes::update_rows(data);
+            return Handled::Yes;
+        }
+        if let Some(pid) = cmd.get(TOGGLE_PROCESS) {
+            if !data.collapsed_processes.remove(pid) {
+                data.collapsed_processes.insert(*pid);
+            }
+            ui::processes::update_rows(data);
+            return Handled::Yes;
+        }
+        if let Some(id) = cmd.get(CHART_SENSOR) {
+            if !data.charted_sensors.remove(id) {
+                data.charted_sensors.insert(id.clone());
+            }
+            ui::sensors::update_rows(data);
+            return Handled::Yes;
+        }
+        if let Some(path) = cmd.get(SELECT_CGROUP) {
+            data.selected_cgroup = Some(path.clone());
+            data.share_cgroup_focus();
+            ui::cgroups::update_rows(data);
+            return Handled::Yes;
+        }
+        if let Some(path) = cmd.get(TOGGLE_CGROUP) {
+            if !data.expanded_cgroups.remove(path) {
+                data.expanded_cgroups.insert(path.clone());
+            }
+            data.share_cgroup_focus();
+            ui::cgroups::update_rows(data);
+            return Handled::Yes;
+        }
+        Handled::No
+    }
+}
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let options = match Options::parse(std::env::args().skip(1)) {
+        Ok(options) => options,
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(2);
+        }
+    };
+    let settings = Arc::new(Settings::new(options.interval_ms, options.history_size));
+    let process_source = ProcessSource::new();
+    let process_control = ProcessControl::new("/proc");
+    let scheduler = Scheduler::new(settings.clone())
+        .with_source(SystemSource::new("/proc", "/sys"))
+        .with_source(GpuSource::new(options.fake_gpu, "/sys", "/proc"))
+        .with_source(process_source)
+        .with_source(NetworkSource::new("/sys"))
+        .with_source(DiskIoSource::new("/proc", "/sys"))
+        .with_source(FilesystemSource::new())
+        .with_source(SensorSource::new(&options.hwmon_root))
+        .with_source(PressureSource::new("/proc"))
+        .with_source(CgroupSource::new(&options.cgroup_root));
+
+    if options.list_series {
+        for (source, series) in scheduler.series() {
+            println!("{}", source);
+            for info in series {
+                println!("  {} ({})", info.name, info.unit);
+            }
+        }
+        return Ok(());
+    }
+
     let main_window = WindowDesc::new(ui::build_ui())
         .title(LocalizedString::new("Rust Druid System Monitor"))
         .window_size((900.0, 750.0));
 
-    let launcher = AppLauncher::with_window(main_window);
+    let launcher = AppLauncher::with_window(main_window).delegate(Delegate);
     let sink = launcher.get_external_handle();
+    let snapshot = scheduler.start(sink);
 
-
-    let state = State {
-        system: SystemStats::new(sink.clone()),
-        gpu: GPU::new(sink.clone()),
+    let mut state = State {
+        system: snapshot.system,
+        gpus: snapshot.gpus,
+        gpu_view: None,
+        gpu_process_sort: ui::gpu_processes::default_sort(),
+        processes: snapshot.processes,
+        network: snapshot.network,
+        show_loopback: false,
+        show_virtual_interfaces: false,
+        disks: snapshot.disks,
+        filesystems: snapshot.filesystems,
+        filesystem_sort: ui::filesystems::default_sort(),
+        filesystem_threshold: ui::filesystems::DEFAULT_THRESHOLD,
+        // Temperatures are what people look at first, so chart them from the start
+        charted_sensors: snapshot.sensors.iter()
+            .filter(|sensor| sensor.kind == SensorKind::Temperature)
+            .map(|sensor| sensor.id.clone())
+            .collect(),
+        sensors: snapshot.sensors,
+        pressure: snapshot.pressure,
+        cgroups: snapshot.cgroups,
+        cgroup_sort: ui::cgroups::default_sort(),
+        // Start with the top-level slices listed and everything below them folded away
+        expanded_cgroups: HashSet::from(["/".to_string()]),
+        selected_cgroup: Some("/".to_string()),
+        limits_cgroup: options.limits_cgroup.or_else(|| own_cgroup("/proc")),
+        use_limits: options.container_limits,
+        process_sort: ui::processes::default_sort(),
+        process_tree: false,
+        collapsed_processes: HashSet::new(),
+        selected_pid: None,
+        nice: 0.0,
+        affinity: String::new(),
+        process_status: String::new(),
+        process_control,
+        gpu_process_rows: Vector::new(),
+        process_rows: Vector::new(),
+        filesystem_rows: Vector::new(),
+        sensor_rows: Vector::new(),
+        cgroup_rows: Vector::new(),
+        settings,
     };
+    state.update_tables();
+    state.share_cgroup_focus();
 
     launcher.launch(state).expect("Failed to launch app");
     Err(Box::new(Error::new(ErrorKind::Other, "Failed to launch app")))
@@ -0,0 +1,126 @@
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use sysinfo::Networks;
+use crate::history::TimeSeries;
+use crate::sampler::{MetricSource, SeriesInfo, Snapshot, SourceError};
+use crate::settings::Settings;
+use crate::sysfs;
+
+// ARPHRD_LOOPBACK from <linux/if_arp.h>
+const ARPHRD_LOOPBACK: u64 = 772;
+
+// Throughput of one network interface, per second
+#[derive(Clone, Debug)]
+pub (crate) struct NetworkInterface {
+    pub (crate) name: String,
+    pub (crate) loopback: bool,
+    // Software interfaces such as bridges, veths and tunnels, which have no backing device
+    pub (crate) is_virtual: bool,
+    pub (crate) rx_bytes_history: TimeSeries,
+    pub (crate) tx_bytes_history: TimeSeries,
+    pub (crate) rx_packets_history: TimeSeries,
+    pub (crate) tx_packets_history: TimeSeries,
+}
+
+impl NetworkInterface {
+    fn new(sys_root: &Path, name: &str, history_size: usize) -> Self {
+        let class = sys_root.join("class/net").join(name);
+        Self {
+            name: name.to_string(),
+            loopback: sysfs::read_u64(class.join("type")) == Some(ARPHRD_LOOPBACK),
+            is_virtual: !class.join("device").exists(),
+            rx_bytes_history: TimeSeries::new(history_size),
+            tx_bytes_history: TimeSeries::new(history_size),
+            rx_packets_history: TimeSeries::new(history_size),
+            tx_packets_history: TimeSeries::new(history_size),
+        }
+    }
+
+    fn set_capacity(&mut self, capacity: usize) {
+        self.rx_bytes_history.set_capacity(capacity);
+        self.tx_bytes_history.set_capacity(capacity);
+        self.rx_packets_history.set_capacity(capacity);
+        self.tx_packets_history.set_capacity(capacity);
+    }
+}
+
+// Per-interface counters from sysinfo, classified using <sys_root>/class/net
+pub (crate) struct NetworkSource {
+    networks: Networks,
+    sys_root: PathBuf,
+    last_refresh: Instant,
+}
+
+impl NetworkSource {
+    pub (crate) fn new(sys_root: impl AsRef<Path>) -> Self {
+        Self {
+            networks: Networks::new(),
+            sys_root: sys_root.as_ref().to_path_buf(),
+            last_refresh: Instant::now(),
+        }
+    }
+}
+
+impl MetricSource for NetworkSource {
+    fn name(&self) -> &'static str {
+        "network"
+    }
+
+    fn init(&mut self, snapshot: &mut Snapshot, settings: &Settings) -> Result<(), SourceError> {
+        // Rates are the change since the previous refresh, so take the first one now
+        self.networks.refresh(true);
+        self.last_refresh = Instant::now();
+
+        let mut names: Vec<&String> = self.networks.list().keys().collect();
+        names.sort();
+        snapshot.network = names.into_iter()
+            .map(|name| NetworkInterface::new(&self.sys_root, name, settings.history_size()))
+            .collect();
+        Ok(())
+    }
+
+    fn sample(&mut self, snapshot: &mut Snapshot, settings: &Settings) -> Result<(), SourceError> {
+        self.networks.refresh(true);
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_refresh).as_secs_f64();
+        self.last_refresh = now;
+        if elapsed <= 0.0 {
+            return Ok(());
+        }
+
+        // Interfaces come and go as containers and VPNs start and stop
+        let list = self.networks.list();
+        snapshot.network.retain(|interface| list.contains_key(&interface.name));
+        for name in list.keys() {
+            if !snapshot.network.iter().any(|interface| &interface.name == name) {
+                let interface = NetworkInterface::new(&self.sys_root, name, settings.history_size());
+                let position = snapshot.network.iter().position(|other| other.name > *name);
+                snapshot.network.insert(position.unwrap_or(snapshot.network.len()), interface);
+            }
+        }
+
+        for interface in snapshot.network.iter_mut() {
+            let Some(data) = list.get(&interface.name) else { continue };
+            interface.set_capacity(settings.history_size());
+            interface.rx_bytes_history.push(data.received() as f64 / elapsed);
+            interface.tx_bytes_history.push(data.transmitted() as f64 / elapsed);
+            interface.rx_packets_history.push(data.packets_received() as f64 / elapsed);
+            interface.tx_packets_history.push(data.packets_transmitted() as f64 / elapsed);
+        }
+        Ok(())
+    }
+
+    fn series(&self) -> Vec<SeriesInfo> {
+        let mut names: Vec<&String> = self.networks.list().keys().collect();
+        names.sort();
+        names.into_iter()
+            .flat_map(|name| [
+                SeriesInfo::new(format!("network.{}.rx", name), "B/s"),
+                SeriesInfo::new(format!("network.{}.tx", name), "B/s"),
+                SeriesInfo::new(format!("network.{}.rx_packets", name), "packets/s"),
+                SeriesInfo::new(format!("network.{}.tx_packets", name), "packets/s"),
+            ])
+            .collect()
+    }
+}
+
@@ -0,0 +1,209 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use im::Vector;
+use crate::history::TimeSeries;
+use crate::sampler::{MetricSource, SeriesInfo, Snapshot, SourceError};
+use crate::settings::Settings;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub (crate) enum Resource {
+    Cpu,
+    Memory,
+    Io,
+}
+
+impl Resource {
+    pub (crate) const ALL: [Resource; 3] = [Resource::Cpu, Resource::Memory, Resource::Io];
+
+    // File under <proc_root>/pressure, also used as the series name
+    pub (crate) fn file_name(self) -> &'static str {
+        match self {
+            Resource::Cpu => "cpu",
+            Resource::Memory => "memory",
+            Resource::Io => "io",
+        }
+    }
+
+    pub (crate) fn title(self) -> &'static str {
+        match self {
+            Resource::Cpu => "CPU",
+            Resource::Memory => "Memory",
+            Resource::Io => "I/O",
+        }
+    }
+}
+
+// One line of a pressure file: the kernel's running averages, in percent, and the total stall
+// time since boot in microseconds
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub (crate) struct PressureLine {
+    pub (crate) avg10: f64,
+    pub (crate) avg60: f64,
+    pub (crate) avg300: f64,
+    pub (crate) total: u64,
+}
+
+// "some" is time at least one task was stalled on the resource, "full" time all non-idle tasks
+// were. CPU has no "full" line before Linux 5.13.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub (crate) struct PressureReading {
+    pub (crate) some: PressureLine,
+    pub (crate) full: Option<PressureLine>,
+}
+
+impl PressureReading {
+    // Percentage of the time between two readings that was stalled, for some and full
+    pub (crate) fn stall_rates(&self, last: &PressureReading, elapsed: f64) -> (f64, Option<f64>) {
+        let rate = |current: u64, last: u64| (current.saturating_sub(last) as f64 / (elapsed * 1e6) * 100.0).min(100.0);
+        let full = match (self.full, last.full) {
+            (Some(current), Some(last)) => Some(rate(current.total, last.total)),
+            _ => None,
+        };
+        (rate(self.some.total, last.some.total), full)
+    }
+}
+
+// e.g. "some avg10=0.12 avg60=0.05 avg300=0.01 total=123456"
+fn parse_pressure_line(line: &str) -> Option<(&str, PressureLine)> {
+    let mut fields = line.split_whitespace();
+    let kind = fields.next()?;
+    let mut parsed = PressureLine::default();
+    for field in fields {
+        let Some((key, value)) = field.split_once('=') else { continue };
+        match key {
+            "avg10" => parsed.avg10 = value.parse().ok()?,
+            "avg60" => parsed.avg60 = value.parse().ok()?,
+            "avg300" => parsed.avg300 = value.parse().ok()?,
+            "total" => parsed.total = value.parse().ok()?,
+            _ => {}
+        }
+    }
+    Some((kind, parsed))
+}
+
+// The contents of a PSI file, either system wide under /proc/pressure or a cgroup's *.pressure
+pub (crate) fn parse_pressure(text: &str) -> Option<PressureReading> {
+    let mut some = None;
+    let mut full = None;
+    for (kind, line) in text.lines().filter_map(parse_pressure_line) {
+        match kind {
+            "some" => some = Some(line),
+            "full" => full = Some(line),
+            _ => {}
+        }
+    }
+    Some(PressureReading { some: some?, full })
+}
+
+// Stall information for one resource over time
+#[derive(Clone, Debug)]
+pub (crate) struct Pressure {
+    pub (crate) resource: Resource,
+    pub (crate) latest: PressureReading,
+    // Percentage of each interval spent stalled
+    pub (crate) some_history: TimeSeries,
+    pub (crate) full_history: TimeSeries,
+}
+
+// Pressure stall information from <proc_root>/pressure. Kernels before 4.20, or built or booted
+// without PSI, don't have it, which disables the source.
+pub (crate) struct PressureSource {
+    proc_root: PathBuf,
+    last_sample: Instant,
+}
+
+impl PressureSource {
+    pub (crate) fn new(proc_root: impl AsRef<Path>) -> Self {
+        Self { proc_root: proc_root.as_ref().to_path_buf(), last_sample: Instant::now() }
+    }
+
+    fn read(&self, resource: Resource) -> Result<PressureReading, SourceError> {
+        let path = self.proc_root.join("pressure").join(resource.file_name());
+        // With psi=0 on the kernel command line the files exist but can't be read
+        let text = fs::read_to_string(&path).map_err(|err| format!("{}: {}", path.display(), err))?;
+        parse_pressure(&text).ok_or_else(|| format!("{}: unrecognised format", path.display()).into())
+    }
+}
+
+impl MetricSource for PressureSource {
+    fn name(&self) -> &'static str {
+        "pressure"
+    }
+
+    fn init(&mut self, snapshot: &mut Snapshot, settings: &Settings) -> Result<(), SourceError> {
+        snapshot.pressure = Resource::ALL.iter()
+            .map(|resource| Ok(Pressure {
+                resource: *resource,
+                latest: self.read(*resource)?,
+                some_history: TimeSeries::new(settings.history_size()),
+                full_history: TimeSeries::new(settings.history_size()),
+            }))
+            .collect::<Result<Vector<_>, SourceError>>()?;
+        self.last_sample = Instant::now();
+        Ok(())
+    }
+
+    fn sample(&mut self, snapshot: &mut Snapshot, settings: &Settings) -> Result<(), SourceError> {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last_sample).as_secs_f64();
+        self.last_sample = now;
+
+        for pressure in snapshot.pressure.iter_mut() {
+            let reading = self.read(pressure.resource)?;
+            pressure.some_history.set_capacity(settings.history_size());
+            pressure.full_history.set_capacity(settings.history_size());
+            if elapsed > 0.0 {
+                let (some, full) = reading.stall_rates(&pressure.latest, elapsed);
+                pressure.some_history.push(some);
+                if let Some(full) = full {
+                    pressure.full_history.push(full);
+                }
+            }
+            pressure.latest = reading;
+        }
+        Ok(())
+    }
+
+    fn series(&self) -> Vec<SeriesInfo> {
+        Resource::ALL.iter()
+            .flat_map(|resource| [
+                SeriesInfo::new(format!("pressure.{}.some", resource.file_name()), "%"),
+                SeriesInfo::new(format!("pressure.{}.full", resource.file_name()), "%"),
+            ])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_pressure() {
+        let text = "some avg10=1.53 avg60=0.87 avg300=0.25 total=3358465\nfull avg10=0.00 avg60=0.13 avg300=0.04 total=1160134\n";
+        let reading = parse_pressure(text).unwrap();
+        assert_eq!(reading.some, PressureLine { avg10: 1.53, avg60: 0.87, avg300: 0.25, total: 3358465 });
+        assert_eq!(reading.full, Some(PressureLine { avg10: 0.0, avg60: 0.13, avg300: 0.04, total: 1160134 }));
+
+        // /proc/pressure/cpu before Linux 5.13
+        let reading = parse_pressure("some avg10=0.00 avg60=0.00 avg300=0.00 total=8027\n").unwrap();
+        assert_eq!(reading.some.total, 8027);
+        assert_eq!(reading.full, None);
+
+        assert_eq!(parse_pressure(""), None);
+        assert_eq!(parse_pressure("some avg10=x avg60=0.00 avg300=0.00 total=0\n"), None);
+    }
+
+    #[test]
+    fn stall_rates_are_a_share_of_the_interval() {
+        let line = |total| PressureLine { total, ..PressureLine::default() };
+        let last = PressureReading { some: line(1_000_000), full: Some(line(500_000)) };
+        let current = PressureReading { some: line(1_500_000), full: Some(line(600_000)) };
+        // 0.5 s and 0.1 s stalled over 2 s
+        assert_eq!(current.stall_rates(&last, 2.0), (25.0, Some(5.0)));
+        // Capped when the counter moved further than the interval, e.g. after a delayed sample
+        let current = PressureReading { some: line(9_000_000), full: None };
+        assert_eq!(current.stall_rates(&last, 2.0), (100.0, None));
+    }
+}
@@ -0,0 +1,407 @@
+use std::collections::HashSet;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+use druid::{Data, Lens};
+use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, Signal, System, Uid, UpdateKind, Users};
+use crate::sampler::{MetricSource, SeriesInfo, Snapshot, SourceError};
+use crate::settings::Settings;
+use crate::sysfs;
+
+// One row of the process table
+#[derive(Clone, Data, Lens, Debug)]
+pub (crate) struct ProcessInfo {
+    pub (crate) pid: u32,
+    pub (crate) parent: Option<u32>,
+    pub (crate) name: String,
+    pub (crate) user: String,
+    // Percent of one core, so a busy multi-threaded process can exceed 100
+    pub (crate) cpu: f64,
+    pub (crate) rss: f64,
+    pub (crate) virtual_mem: f64,
+    pub (crate) state: String,
+    // Seconds since the Unix epoch
+    pub (crate) start_time: u64,
+    pub (crate) cmdline: String,
+}
+
+// Every process on the system, from sysinfo
+pub (crate) struct ProcessSource {
+    sys: System,
+    users: UserNames,
+}
+
+impl ProcessSource {
+    pub (crate) fn new() -> Self {
+        Self { sys: System::new(), users: UserNames::new() }
+    }
+
+    fn refresh(&mut self) {
+        self.sys.refresh_processes_specifics(
+            ProcessesToUpdate::All,
+            true,
+            // Threads would otherwise be listed as children of their process and counted twice
+            ProcessRefreshKind::nothing()
+                .without_tasks()
+                .with_cpu()
+                .with_memory()
+                .with_user(UpdateKind::OnlyIfNotSet)
+                .with_cmd(UpdateKind::OnlyIfNotSet),
+        );
+    }
+}
+
+impl MetricSource for ProcessSource {
+    fn name(&self) -> &'static str {
+        "processes"
+    }
+
+    fn init(&mut self, _snapshot: &mut Snapshot, _settings: &Settings) -> Result<(), SourceError> {
+        // CPU usage is measured between two refreshes, so take the first one now
+        self.refresh();
+        Ok(())
+    }
+
+    fn sample(&mut self, snapshot: &mut Snapshot, _settings: &Settings) -> Result<(), SourceError> {
+        self.refresh();
+
+        let users = &mut self.users;
+        users.start_sample();
+        snapshot.processes = self.sys.processes().iter()
+            .map(|(pid, process)| {
+                let cmdline = process.cmd().iter()
+                    .map(|arg| arg.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join(" ");
+                ProcessInfo {
+                    pid: pid.as_u32(),
+                    parent: process.parent().map(|parent| parent.as_u32()),
+                    name: process.name().to_string_lossy().into_owned(),
+                    user: users.name(process.user_id()),
+                    cpu: process.cpu_usage() as f64,
+                    rss: process.memory() as f64,
+                    virtual_mem: process.virtual_memory() as f64,
+                    state: process.status().to_string(),
+                    start_time: process.start_time(),
+                    cmdline,
+                }
+            })
+            .collect();
+        Ok(())
+    }
+
+    fn series(&self) -> Vec<SeriesInfo> {
+        // The process table is a list rather than a history
+        Vec::new()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub (crate) enum ProcessAction {
+    Signal(Signal),
+    Renice(i32),
+    Affinity(Vec<usize>),
+}
+
+impl ProcessAction {
+    fn describe(&self) -> String {
+        match self {
+            ProcessAction::Signal(Signal::Term) => "SIGTERM".to_string(),
+            ProcessAction::Signal(Signal::Kill) => "SIGKILL".to_string(),
+            ProcessAction::Signal(Signal::Stop) => "SIGSTOP".to_string(),
+            ProcessAction::Signal(Signal::Continue) => "SIGCONT".to_string(),
+            ProcessAction::Signal(signal) => format!("{:?}", signal),
+            ProcessAction::Renice(nice) => format!("nice {}", nice),
+            ProcessAction::Affinity(cpus) => format!("affinity {:?}", cpus),
+        }
+    }
+}
+
+// Sends signals and scheduling changes to the processes listed in the process table. Works from
+// /proc directly, so an action never waits for the sampler to finish a refresh.
+#[derive(Clone, Debug)]
+pub (crate) struct ProcessControl {
+    proc_root: PathBuf,
+}
+
+impl ProcessControl {
+    pub (crate) fn new(proc_root: impl AsRef<Path>) -> Self {
+        Self { proc_root: proc_root.as_ref().to_path_buf() }
+    }
+
+    // `start_time` guards against the pid having been reused since the table was drawn. Returns
+    // a message for the UI either way.
+    pub (crate) fn apply(&self, pid: u32, start_time: u64, action: &ProcessAction) -> Result<String, String> {
+        if self.start_time(pid) != Some(start_time) {
+            return Err(format!("Process {} has exited", pid));
+        }
+        let name = sysfs::read_string(self.proc_root.join(format!("{}/comm", pid))).unwrap_or_default();
+        let target = format!("{} ({})", pid, name);
+
+        let result = match action {
+            ProcessAction::Signal(signal) => match signal_number(*signal) {
+                // SAFETY: kill only reads its integer arguments
+                Some(signal) => check(unsafe { libc::kill(pid as libc::pid_t, signal) }),
+                None => Err(io::Error::new(io::ErrorKind::Unsupported, "signal not supported")),
+            },
+            ProcessAction::Renice(nice) => self.for_each_thread(pid, |tid| {
+                // SAFETY: setpriority only reads its integer arguments
+                check(unsafe { libc::setpriority(libc::PRIO_PROCESS, tid, *nice) })
+            }),
+            ProcessAction::Affinity(cpus) => self.set_affinity(pid, cpus),
+        };
+
+        match result {
+            Ok(()) => Ok(format!("Applied {} to {}", action.describe(), target)),
+            Err(err) => Err(format!("Failed to apply {} to {}: {}", action.describe(), target, err)),
+        }
+    }
+
+    // Start time in seconds since the Unix epoch, worked out the way sysinfo does for the table
+    fn start_time(&self, pid: u32) -> Option<u64> {
+        let stat = fs::read_to_string(self.proc_root.join(format!("{}/stat", pid))).ok()?;
+        let system_stat = fs::read_to_string(self.proc_root.join("stat")).ok()?;
+        // SAFETY: sysconf only reads its integer argument
+        let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+        parse_start_time(&stat, &system_stat, u64::try_from(ticks_per_second).ok()?)
+    }
+
+    // Nice values and affinity are per thread on Linux, so apply them to every thread of the
+    // process. Threads exit all the time, so one gone between listing and applying (ESRCH) is
+    // skipped; only the main thread has to succeed for the process to count as changed.
+    fn for_each_thread(&self, pid: u32, mut f: impl FnMut(libc::id_t) -> io::Result<()>) -> io::Result<()> {
+        f(pid)?;
+        let Ok(tasks) = fs::read_dir(self.proc_root.join(format!("{}/task", pid))) else { return Ok(()) };
+        tasks.flatten()
+            .filter_map(|task| task.file_name().to_str()?.parse().ok())
+            .filter(|&tid| tid != pid)
+            .try_for_each(|tid| match f(tid) {
+                Err(err) if err.raw_os_error() == Some(libc::ESRCH) => Ok(()),
+                result => result,
+            })
+    }
+
+    fn set_affinity(&self, pid: u32, cpus: &[usize]) -> io::Result<()> {
+        if cpus.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "no CPUs given"));
+        }
+        // SAFETY: cpu_set_t is a plain bitmask, for which all zeroes is the empty set
+        let mut set: libc::cpu_set_t = unsafe { std::mem::zeroed() };
+        for &cpu in cpus {
+            if cpu >= libc::CPU_SETSIZE as usize {
+                return Err(io::Error::new(io::ErrorKind::InvalidInput, format!("CPU {} is out of range", cpu)));
+            }
+            // SAFETY: `cpu` was checked against the size of the set above
+            unsafe { libc::CPU_SET(cpu, &mut set) };
+        }
+        self.for_each_thread(pid, |tid| {
+            // SAFETY: `set` is a valid cpu_set_t of the size passed
+            check(unsafe { libc::sched_setaffinity(tid as libc::pid_t, size_of::<libc::cpu_set_t>(), &set) })
+        })
+    }
+}
+
+// Start time from /proc/<pid>/stat (field 22, in clock ticks since boot) and the boot time from
+// the `btime` line of /proc/stat
+fn parse_start_time(stat: &str, system_stat: &str, ticks_per_second: u64) -> Option<u64> {
+    // The command name may itself contain spaces and parentheses, so start after the last ')'
+    let (_, fields) = stat.rsplit_once(')')?;
+    let ticks: u64 = fields.split_whitespace().nth(19)?.parse().ok()?;
+    let boot_time: u64 = system_stat.lines().find_map(|line| line.strip_prefix("btime "))?.trim().parse().ok()?;
+    Some(boot_time + ticks / ticks_per_second.max(1))
+}
+
+fn signal_number(signal: Signal) -> Option<libc::c_int> {
+    Some(match signal {
+        Signal::Hangup => libc::SIGHUP,
+        Signal::Interrupt => libc::SIGINT,
+        Signal::Quit => libc::SIGQUIT,
+        Signal::Kill => libc::SIGKILL,
+        Signal::Term => libc::SIGTERM,
+        Signal::Stop => libc::SIGSTOP,
+        Signal::Continue => libc::SIGCONT,
+        Signal::User1 => libc::SIGUSR1,
+        Signal::User2 => libc::SIGUSR2,
+        _ => return None,
+    })
+}
+
+fn check(ret: libc::c_int) -> io::Result<()> {
+    if ret == -1 { Err(io::Error::last_os_error()) } else { Ok(()) }
+}
+
+// Parse a CPU list such as "0-3,6". CPUs are bounded by CPU_SETSIZE here, before a range is
+// expanded, so a typo like "0-99999999999" can't exhaust memory.
+pub (crate) fn parse_cpu_list(text: &str) -> Result<Vec<usize>, String> {
+    let mut cpus = Vec::new();
+    for part in text.split(',').map(str::trim).filter(|part| !part.is_empty()) {
+        let parse = |cpu: &str| {
+            let cpu = cpu.trim();
+            match cpu.parse::<usize>() {
+                Ok(n) if n < libc::CPU_SETSIZE as usize => Ok(n),
+                Ok(_) => Err(format!("CPU {} is out of range (at most {})", cpu, libc::CPU_SETSIZE - 1)),
+                Err(_) => Err(format!("invalid CPU `{}`", cpu)),
+            }
+        };
+        match part.split_once('-') {
+            Some((first, last)) => {
+                let (first, last) = (parse(first)?, parse(last)?);
+                if first > last {
+                    return Err(format!("range `{}` is reversed; write it as {}-{}", part, last, first));
+                }
+                cpus.extend(first..=last);
+            }
+            None => cpus.push(parse(part)?),
+        }
+    }
+    if cpus.is_empty() {
+        return Err("no CPUs given".to_string());
+    }
+    Ok(cpus)
+}
+
+// Names of the users owning processes, falling back to the numeric id. Re-reads the user list at
+// most once per sample when an unknown owner turns up, and remembers uids without a passwd entry
+// (common with user-namespaced containers) so they don't trigger a re-read every sample.
+pub (crate) struct UserNames {
+    users: Users,
+    refreshed: bool,
+    unknown: HashSet<Uid>,
+}
+
+impl UserNames {
+    pub (crate) fn new() -> Self {
+        Self { users: Users::new_with_refreshed_list(), refreshed: false, unknown: HashSet::new() }
+    }
+
+    // Allow one more re-read of the user list, for the next sample
+    pub (crate) fn start_sample(&mut self) {
+        self.refreshed = false;
+    }
+
+    pub (crate) fn name(&mut self, uid: Option<&Uid>) -> String {
+        let Some(uid) = uid else { return "?".to_string() };
+        if self.users.get_user_by_id(uid).is_none() && !self.unknown.contains(uid) && !self.refreshed {
+            self.users.refresh();
+            self.refreshed = true;
+            // The new list may cover uids that were missing before
+            self.unknown.clear();
+        }
+        match self.users.get_user_by_id(uid) {
+            Some(user) => user.name().to_string(),
+            None => {
+                self.unknown.insert(uid.clone());
+                (**uid).to_string()
+            }
+        }
+    }
+}
+
+// Time since a process started
+pub (crate) fn format_age(start_time: u64) -> String {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|now| now.as_secs())
+        .unwrap_or(0);
+    format_duration(now.saturating_sub(start_time))
+}
+
+// A number of seconds as e.g. "3d 04:12" or "12:30"
+pub (crate) fn format_duration(seconds: u64) -> String {
+    let (days, hours, minutes, seconds) = (seconds / 86400, seconds / 3600 % 24, seconds / 60 % 60, seconds % 60);
+    if days > 0 {
+        format!("{}d {:02}:{:02}", days, hours, minutes)
+    } else if hours > 0 {
+        format!("{}:{:02}:{:02}", hours, minutes, seconds)
+    } else {
+        format!("{}:{:02}", minutes, seconds)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sysfs::TempTree;
+
+    // /proc/<pid>/stat of a process started 12345 ticks after boot
+    const STAT: &str = "4242 (tmux: server (1)) S 1 4242 4242 0 -1 4194624 1208 0 0 0 16 9 0 0 20 0 1 0 12345 11993088 1152 18446744073709551615 1 1 0 0 0 0 0 3 134763015 0 0 0 17 2 0 0 0 0 0 0 0 0 0 0 0 0 0\n";
+    const SYSTEM_STAT: &str = "cpu  1 2 3 4 5 6 7 0 0 0\nintr 1\nctxt 2\nbtime 1700000000\nprocesses 3\n";
+
+    #[test]
+    fn parses_start_time() {
+        assert_eq!(parse_start_time(STAT, SYSTEM_STAT, 100), Some(1700000123));
+        assert_eq!(parse_start_time(STAT, "cpu  1 2 3 4\n", 100), None);
+        assert_eq!(parse_start_time("4242 (tmux) S 1 4242", SYSTEM_STAT, 100), None);
+    }
+
+    #[test]
+    fn refuses_a_reused_pid() {
+        let proc = TempTree::new();
+        proc.write("stat", SYSTEM_STAT)
+            .write("4242/stat", STAT)
+            .write("4242/comm", "tmux: server\n");
+        let control = ProcessControl::new(proc.path());
+
+        // Checked before anything is sent, so nothing reaches the real pid 4242
+        let action = ProcessAction::Signal(Signal::Term);
+        assert_eq!(control.apply(4242, 1700000000, &action), Err("Process 4242 has exited".to_string()));
+        assert_eq!(control.apply(4243, 1700000123, &action), Err("Process 4243 has exited".to_string()));
+
+        // With the right start time the action goes ahead; SIGALRM isn't offered, so nothing is sent
+        // SAFETY: sysconf only reads its integer argument
+        let ticks_per_second = unsafe { libc::sysconf(libc::_SC_CLK_TCK) } as u64;
+        let start_time = 1700000000 + 12345 / ticks_per_second;
+        assert_eq!(control.start_time(4242), Some(start_time));
+        assert_eq!(
+            control.apply(4242, start_time, &ProcessAction::Signal(Signal::Alarm)),
+            Err("Failed to apply Alarm to 4242 (tmux: server): signal not supported".to_string()),
+        );
+    }
+
+    #[test]
+    fn skips_threads_that_have_exited() {
+        let proc = TempTree::new();
+        proc.write("4242/task/4242/stat", "")
+            .write("4242/task/4243/stat", "")
+            .write("4242/task/4244/stat", "");
+        let control = ProcessControl::new(proc.path());
+        let gone = || io::Error::from_raw_os_error(libc::ESRCH);
+
+        let mut seen = Vec::new();
+        let result = control.for_each_thread(4242, |tid| {
+            seen.push(tid);
+            if tid == 4243 { Err(gone()) } else { Ok(()) }
+        });
+        assert!(result.is_ok());
+        seen.sort();
+        assert_eq!(seen, [4242, 4243, 4244]);
+
+        // The main thread has to succeed, and other errors still fail the action
+        let result = control.for_each_thread(4242, |tid| if tid == 4242 { Err(gone()) } else { Ok(()) });
+        assert_eq!(result.unwrap_err().raw_os_error(), Some(libc::ESRCH));
+        let result = control.for_each_thread(4242, |tid| {
+            if tid == 4244 { Err(io::Error::from_raw_os_error(libc::EPERM)) } else { Ok(()) }
+        });
+        assert_eq!(result.unwrap_err().raw_os_error(), Some(libc::EPERM));
+    }
+
+    #[test]
+    fn parses_cpu_lists() {
+        // The format of /sys/devices/system/cpu/online and taskset -c
+        assert_eq!(parse_cpu_list("0-3,6\n"), Ok(vec![0, 1, 2, 3, 6]));
+        assert_eq!(parse_cpu_list(" 2 , 4 - 5 "), Ok(vec![2, 4, 5]));
+        assert_eq!(parse_cpu_list("7"), Ok(vec![7]));
+    }
+
+    #[test]
+    fn rejects_bad_cpu_lists() {
+        assert_eq!(parse_cpu_list(""), Err("no CPUs given".to_string()));
+        assert_eq!(parse_cpu_list("0,x"), Err("invalid CPU `x`".to_string()));
+        assert_eq!(parse_cpu_list("3-1"), Err("range `3-1` is reversed; write it as 1-3".to_string()));
+        // Bounded before the range is expanded, so this fails instead of exhausting memory
+        assert!(parse_cpu_list("0-99999999999").unwrap_err().contains("out of range"));
+        assert!(parse_cpu_list(&libc::CPU_SETSIZE.to_string()).is_err());
+        assert!(parse_cpu_list(&(libc::CPU_SETSIZE - 1).to_string()).is_ok());
+    }
+}
@@ -0,0 +1,261 @@
+use std::sync::Arc;
+use std::thread;
+use std::time::Instant;
+use druid::{ExtEventSink, Target};
+use im::Vector;
+use crate::cgroups::Cgroup;
+use crate::diskio::BlockDevice;
+use crate::filesystems::Filesystem;
+use crate::gpu::GPU;
+use crate::network::NetworkInterface;
+use crate::pressure::Pressure;
+use crate::processes::ProcessInfo;
+use crate::sensors::Sensor;
+use crate::settings::Settings;
+use crate::system::SystemStats;
+use crate::UPDATE_SNAPSHOT;
+
+pub (crate) type SourceError = Box<dyn std::error::Error + Send + Sync>;
+
+// Everything the collectors publish to the UI in one update; each source owns its own fields
+#[derive(Clone, Debug, Default)]
+pub (crate) struct Snapshot {
+    pub (crate) system: SystemStats,
+    pub (crate) gpus: Vector<GPU>,
+    pub (crate) processes: Vector<ProcessInfo>,
+    pub (crate) network: Vector<NetworkInterface>,
+    pub (crate) disks: Vector<BlockDevice>,
+    pub (crate) filesystems: Vector<Filesystem>,
+    pub (crate) sensors: Vector<Sensor>,
+    pub (crate) pressure: Vector<Pressure>,
+    pub (crate) cgroups: Vector<Cgroup>,
+}
+
+// A series a source records, for `--list-series`
+#[derive(Clone, Debug)]
+pub (crate) struct SeriesInfo {
+    pub (crate) name: String,
+    pub (crate) unit: &'static str,
+}
+
+impl SeriesInfo {
+    pub (crate) fn new(name: impl Into<String>, unit: &'static str) -> Self {
+        Self { name: name.into(), unit }
+    }
+}
+
+// A collector driven by the Scheduler. Implement this to add a new kind of metric rather than
+// starting another sampling thread.
+pub (crate) trait MetricSource: Send {
+    fn name(&self) -> &'static str;
+
+    // Called once before sampling starts, to size the source's part of the snapshot. An error
+    // disables the source for the rest of the run.
+    fn init(&mut self, snapshot: &mut Snapshot, settings: &Settings) -> Result<(), SourceError>;
+
+    // Take one reading and append it to the source's histories in the snapshot
+    fn sample(&mut self, snapshot: &mut Snapshot, settings: &Settings) -> Result<(), SourceError>;
+
+    fn series(&self) -> Vec<SeriesInfo>;
+}
+
+// Drives every source from a single thread and publishes the combined snapshot each tick.
+// Sources run one after another, so a slow source holds up all the others: statvfs in the
+// filesystem source, and sysinfo's own disk refresh, can block indefinitely on a dead NFS mount,
+// which freezes every graph until the mount answers again.
+pub (crate) struct Scheduler {
+    sources: Vec<Box<dyn MetricSource>>,
+    settings: Arc<Settings>,
+}
+
+impl Scheduler {
+    pub (crate) fn new(settings: Arc<Settings>) -> Self {
+        Self { sources: Vec::new(), settings }
+    }
+
+    pub (crate) fn with_source(mut self, source: impl MetricSource + 'static) -> Self {
+        self.sources.push(Box::new(source));
+        self
+    }
+
+    // Initialise every source and list what it records, without starting the sampler
+    pub (crate) fn series(self) -> Vec<(&'static str, Vec<SeriesInfo>)> {
+        let mut snapshot = Snapshot::default();
+        Sources::init(self.sources, &mut snapshot, &self.settings).sources
+            .iter()
+            .map(|source| (source.name(), source.series()))
+            .collect()
+    }
+
+    // Initialise the sources on the calling thread so the first snapshot can seed the UI state,
+    // then keep sampling them in the background
+    pub (crate) fn start(self, sink: ExtEventSink) -> Snapshot {
+        let Scheduler { sources, settings } = self;
+        let mut snapshot = Snapshot::default();
+        let mut sources = Sources::init(sources, &mut snapshot, &settings);
+        let initial = snapshot.clone();
+
+        thread::spawn(move || {
+            loop {
+                let started = Instant::now();
+                for err in sources.sample(&mut snapshot, &settings) {
+                    eprintln!("{}", err);
+                }
+
+                // Cloning only bumps reference counts on the histories
+                if sink.submit_command(UPDATE_SNAPSHOT, snapshot.clone(), Target::Auto).is_err() {
+                    // The window has closed
+                    return;
+                }
+
+                thread::sleep(settings.interval().saturating_sub(started.elapsed()));
+            }
+        });
+
+        initial
+    }
+}
+
+// The sources that initialised, with the last error each one reported
+struct Sources {
+    sources: Vec<Box<dyn MetricSource>>,
+    last_errors: Vec<Option<String>>,
+}
+
+impl Sources {
+    // Sources that fail to initialise are reported and dropped
+    fn init(sources: Vec<Box<dyn MetricSource>>, snapshot: &mut Snapshot, settings: &Settings) -> Self {
+        let sources: Vec<Box<dyn MetricSource>> = sources
+            .into_iter()
+            .filter_map(|mut source| match source.init(snapshot, settings) {
+                Ok(()) => Some(source),
+                Err(err) => {
+                    eprintln!("{} disabled: {}", source.name(), err);
+                    None
+                }
+            })
+            .collect();
+        let last_errors = vec![None; sources.len()];
+        Self { sources, last_errors }
+    }
+
+    // Sample every source once, returning the errors to report. An error is only returned when
+    // it changes, so a persistent failure doesn't flood stderr.
+    fn sample(&mut self, snapshot: &mut Snapshot, settings: &Settings) -> Vec<String> {
+        let mut report = Vec::new();
+        for (source, last_error) in self.sources.iter_mut().zip(self.last_errors.iter_mut()) {
+            let error = source.sample(snapshot, settings).err().map(|err| err.to_string());
+            if let Some(err) = &error && error != *last_error {
+                report.push(format!("{} sample failed: {}", source.name(), err));
+            }
+            *last_error = error;
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Counts its samples into one field of the snapshot and fails on the scripted ticks
+    struct StubSource {
+        name: &'static str,
+        init_error: Option<&'static str>,
+        sample_errors: Vec<Option<&'static str>>,
+        samples: usize,
+        field: fn(&mut Snapshot) -> &mut u64,
+    }
+
+    impl StubSource {
+        fn new(name: &'static str, field: fn(&mut Snapshot) -> &mut u64) -> Self {
+            Self { name, init_error: None, sample_errors: Vec::new(), samples: 0, field }
+        }
+    }
+
+    impl MetricSource for StubSource {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn init(&mut self, snapshot: &mut Snapshot, _settings: &Settings) -> Result<(), SourceError> {
+            match self.init_error {
+                Some(err) => Err(err.into()),
+                None => {
+                    *(self.field)(snapshot) = 0;
+                    Ok(())
+                }
+            }
+        }
+
+        fn sample(&mut self, snapshot: &mut Snapshot, _settings: &Settings) -> Result<(), SourceError> {
+            self.samples += 1;
+            *(self.field)(snapshot) = self.samples as u64;
+            match self.sample_errors.get(self.samples - 1).copied().flatten() {
+                Some(err) => Err(err.into()),
+                None => Ok(()),
+            }
+        }
+
+        fn series(&self) -> Vec<SeriesInfo> {
+            vec![SeriesInfo::new(self.name, "count")]
+        }
+    }
+
+    fn uptime(snapshot: &mut Snapshot) -> &mut u64 {
+        &mut snapshot.system.uptime
+    }
+
+    fn tasks(snapshot: &mut Snapshot) -> &mut u64 {
+        &mut snapshot.system.load.total_tasks
+    }
+
+    fn init(sources: Vec<StubSource>) -> (Sources, Snapshot) {
+        let mut snapshot = Snapshot::default();
+        let sources = sources.into_iter().map(|source| Box::new(source) as Box<dyn MetricSource>).collect();
+        (Sources::init(sources, &mut snapshot, &Settings::new(0, 0)), snapshot)
+    }
+
+    #[test]
+    fn snapshot_combines_every_source() {
+        let (mut sources, mut snapshot) = init(vec![StubSource::new("uptime", uptime), StubSource::new("tasks", tasks)]);
+        let settings = Settings::new(0, 0);
+        for _ in 0..3 {
+            assert!(sources.sample(&mut snapshot, &settings).is_empty());
+        }
+        assert_eq!((snapshot.system.uptime, snapshot.system.load.total_tasks), (3, 3));
+    }
+
+    #[test]
+    fn init_failure_disables_the_source() {
+        let broken = StubSource { init_error: Some("no such device"), ..StubSource::new("tasks", tasks) };
+        let (mut sources, mut snapshot) = init(vec![StubSource::new("uptime", uptime), broken]);
+        assert_eq!(sources.sources.iter().map(|source| source.name()).collect::<Vec<_>>(), ["uptime"]);
+
+        sources.sample(&mut snapshot, &Settings::new(0, 0));
+        assert_eq!((snapshot.system.uptime, snapshot.system.load.total_tasks), (1, 0));
+    }
+
+    #[test]
+    fn sample_errors_are_reported_once() {
+        let flaky = StubSource {
+            sample_errors: vec![None, Some("timed out"), Some("timed out"), Some("gone"), None, Some("gone")],
+            ..StubSource::new("tasks", tasks)
+        };
+        let (mut sources, mut snapshot) = init(vec![flaky, StubSource::new("uptime", uptime)]);
+        let settings = Settings::new(0, 0);
+        let reports: Vec<Vec<String>> = (0..6).map(|_| sources.sample(&mut snapshot, &settings)).collect();
+
+        assert_eq!(reports, [
+            vec![],
+            vec!["tasks sample failed: timed out".to_string()],
+            vec![],
+            vec!["tasks sample failed: gone".to_string()],
+            vec![],
+            // Failing again after a good sample counts as a new error
+            vec!["tasks sample failed: gone".to_string()],
+        ]);
+        // A failing source doesn't stop the others
+        assert_eq!(snapshot.system.uptime, 6);
+    }
+}
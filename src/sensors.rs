@@ -0,0 +1,258 @@
+use std::path::{Path, PathBuf};
+use druid::Data;
+use sysinfo::Components;
+use crate::history::TimeSeries;
+use crate::sampler::{MetricSource, SeriesInfo, Snapshot, SourceError};
+use crate::settings::Settings;
+use crate::sysfs;
+
+pub (crate) const DEFAULT_HWMON_ROOT: &str = "/sys/class/hwmon";
+
+#[derive(Clone, Copy, Data, Debug, PartialEq, Eq, Hash)]
+pub (crate) enum SensorKind {
+    Temperature,
+    Fan,
+    Voltage,
+    Power,
+    Current,
+}
+
+impl SensorKind {
+    pub (crate) const ALL: [SensorKind; 5] = [
+        SensorKind::Temperature,
+        SensorKind::Fan,
+        SensorKind::Voltage,
+        SensorKind::Power,
+        SensorKind::Current,
+    ];
+
+    pub (crate) fn title(self) -> &'static str {
+        match self {
+            SensorKind::Temperature => "Temperatures",
+            SensorKind::Fan => "Fans",
+            SensorKind::Voltage => "Voltages",
+            SensorKind::Power => "Power",
+            SensorKind::Current => "Currents",
+        }
+    }
+
+    pub (crate) fn unit(self) -> &'static str {
+        match self {
+            SensorKind::Temperature => "°C",
+            SensorKind::Fan => "RPM",
+            SensorKind::Voltage => "V",
+            SensorKind::Power => "W",
+            SensorKind::Current => "A",
+        }
+    }
+
+    // hwmon file prefix, and the divisor from its fixed-point unit to ours
+    fn hwmon(self) -> (&'static str, f64) {
+        match self {
+            SensorKind::Temperature => ("temp", 1000.0),
+            SensorKind::Fan => ("fan", 1.0),
+            SensorKind::Voltage => ("in", 1000.0),
+            SensorKind::Power => ("power", 1_000_000.0),
+            SensorKind::Current => ("curr", 1000.0),
+        }
+    }
+}
+
+// One sensor reading over time
+#[derive(Clone, Debug)]
+pub (crate) struct Sensor {
+    // Stable for the session, used to remember which sensors are charted
+    pub (crate) id: String,
+    pub (crate) chip: String,
+    pub (crate) label: String,
+    pub (crate) kind: SensorKind,
+    pub (crate) critical: Option<f64>,
+    pub (crate) history: TimeSeries,
+}
+
+// Where a sensor's readings come from
+enum Input {
+    // An hwmon attribute file and the divisor converting it to the sensor's unit
+    Hwmon(PathBuf, f64),
+    // Index into sysinfo's component list
+    Component(usize),
+}
+
+// Every input under <hwmon_root>/hwmonN, plus any temperatures sysinfo finds that hwmon doesn't
+// already cover. sysinfo always reads the real /sys, so components are skipped when the hwmon
+// root has been pointed somewhere else.
+pub (crate) struct SensorSource {
+    hwmon_root: PathBuf,
+    components: Option<Components>,
+    inputs: Vec<Input>,
+    series: Vec<SeriesInfo>,
+}
+
+impl SensorSource {
+    pub (crate) fn new(hwmon_root: impl AsRef<Path>) -> Self {
+        let hwmon_root = hwmon_root.as_ref().to_path_buf();
+        let components = (hwmon_root == Path::new(DEFAULT_HWMON_ROOT)).then(Components::new_with_refreshed_list);
+        Self { hwmon_root, components, inputs: Vec::new(), series: Vec::new() }
+    }
+
+    fn add(&mut self, sensor: &Sensor, input: Input) {
+        self.series.push(SeriesInfo::new(format!("sensors.{}", sensor.id.replace('/', ".")), sensor.kind.unit()));
+        self.inputs.push(input);
+    }
+
+    fn hwmon_sensors(&mut self, history_size: usize) -> Vec<Sensor> {
+        let mut sensors = Vec::new();
+        for (index, hwmon) in sysfs::numbered_entries(&self.hwmon_root, "hwmon", "") {
+            let chip = sysfs::read_string(hwmon.join("name")).unwrap_or_else(|| format!("hwmon{}", index));
+            for kind in SensorKind::ALL {
+                let (prefix, divisor) = kind.hwmon();
+                // Power meters report an average rather than an instantaneous input on some chips
+                let suffixes: &[&str] = if kind == SensorKind::Power { &["_input", "_average"] } else { &["_input"] };
+                for suffix in suffixes {
+                    for (number, input) in sysfs::numbered_entries(&hwmon, prefix, suffix) {
+                        let name = format!("{}{}", prefix, number);
+                        if kind == SensorKind::Power && *suffix == "_average" && hwmon.join(format!("{}_input", name)).exists() {
+                            continue;
+                        }
+                        let attribute = |attribute: &str| hwmon.join(format!("{}_{}", name, attribute));
+                        let sensor = Sensor {
+                            id: format!("hwmon{}/{}", index, name),
+                            chip: chip.clone(),
+                            label: sysfs::read_string(attribute("label")).unwrap_or_else(|| name.clone()),
+                            kind,
+                            critical: sysfs::read_f64(attribute("crit")).map(|crit| crit / divisor),
+                            history: TimeSeries::new(history_size),
+                        };
+                        self.add(&sensor, Input::Hwmon(input, divisor));
+                        sensors.push(sensor);
+                    }
+                }
+            }
+        }
+        sensors
+    }
+
+    fn component_sensors(&mut self, covered: &[Sensor], history_size: usize) -> Vec<Sensor> {
+        let Some(components) = &self.components else { return Vec::new() };
+        let sensors: Vec<(usize, Sensor)> = components.list().iter().enumerate()
+            // sysinfo labels hwmon temperatures as "<chip> <label>"
+            .filter(|(_, component)| {
+                !covered.iter().any(|sensor| format!("{} {}", sensor.chip, sensor.label) == component.label())
+            })
+            .map(|(index, component)| (index, Sensor {
+                id: format!("component{}", index),
+                chip: "sysinfo".to_string(),
+                label: component.label().to_string(),
+                kind: SensorKind::Temperature,
+                critical: component.critical().map(|crit| crit as f64),
+                history: TimeSeries::new(history_size),
+            }))
+            .collect();
+        sensors.into_iter()
+            .map(|(index, sensor)| {
+                self.add(&sensor, Input::Component(index));
+                sensor
+            })
+            .collect()
+    }
+}
+
+impl MetricSource for SensorSource {
+    fn name(&self) -> &'static str {
+        "sensors"
+    }
+
+    fn init(&mut self, snapshot: &mut Snapshot, settings: &Settings) -> Result<(), SourceError> {
+        let mut sensors = self.hwmon_sensors(settings.history_size());
+        let components = self.component_sensors(&sensors, settings.history_size());
+        sensors.extend(components);
+        if sensors.is_empty() {
+            return Err(format!("no sensors found under {}", self.hwmon_root.display()).into());
+        }
+        snapshot.sensors = sensors.into_iter().collect();
+        Ok(())
+    }
+
+    fn sample(&mut self, snapshot: &mut Snapshot, settings: &Settings) -> Result<(), SourceError> {
+        if let Some(components) = &mut self.components {
+            components.refresh(false);
+        }
+        for (sensor, input) in snapshot.sensors.iter_mut().zip(&self.inputs) {
+            sensor.history.set_capacity(settings.history_size());
+            let value = match input {
+                Input::Hwmon(path, divisor) => sysfs::read_f64(path).map(|raw| raw / divisor),
+                Input::Component(index) => self.components.as_ref()
+                    .and_then(|components| components.list().get(*index))
+                    .and_then(|component| component.temperature())
+                    .map(|celsius| celsius as f64),
+            };
+            // A sensor that fails to read (e.g. on a powered-down device) just misses this sample
+            if let Some(value) = value {
+                sensor.history.push(value);
+            }
+        }
+        Ok(())
+    }
+
+    fn series(&self) -> Vec<SeriesInfo> {
+        self.series.clone()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sysfs::TempTree;
+
+    fn find<'a>(snapshot: &'a Snapshot, id: &str) -> &'a Sensor {
+        snapshot.sensors.iter().find(|sensor| sensor.id == id).unwrap_or_else(|| panic!("no sensor {}", id))
+    }
+
+    #[test]
+    fn reads_sensors_from_a_hwmon_tree() {
+        let hwmon = TempTree::new();
+        hwmon.write("hwmon0/name", "k10temp\n")
+            .write("hwmon0/temp1_input", "45125\n")
+            .write("hwmon0/temp1_label", "Tctl\n")
+            .write("hwmon0/temp1_crit", "95000\n")
+            .write("hwmon2/fan2_input", "812\n")
+            .write("hwmon2/in0_input", "1048\n")
+            .write("hwmon2/curr1_input", "1500\n")
+            // power1 has both, so its average is ignored; power2 only has an average
+            .write("hwmon2/power1_input", "5000000\n")
+            .write("hwmon2/power1_average", "4000000\n")
+            .write("hwmon2/power2_average", "3500000\n");
+
+        let mut source = SensorSource::new(hwmon.path());
+        let settings = Settings::new(200, 10);
+        let mut snapshot = Snapshot::default();
+        source.init(&mut snapshot, &settings).unwrap();
+        source.sample(&mut snapshot, &settings).unwrap();
+
+        let ids: Vec<&str> = snapshot.sensors.iter().map(|sensor| sensor.id.as_str()).collect();
+        assert_eq!(ids, ["hwmon0/temp1", "hwmon2/fan2", "hwmon2/in0", "hwmon2/power1", "hwmon2/power2", "hwmon2/curr1"]);
+
+        let temp = find(&snapshot, "hwmon0/temp1");
+        assert_eq!((temp.chip.as_str(), temp.label.as_str()), ("k10temp", "Tctl"));
+        assert_eq!(temp.critical, Some(95.0));
+        assert_eq!(temp.history.latest(), Some(45.125));
+
+        // Without name or label files, the chip and sensor are named after their files
+        let fan = find(&snapshot, "hwmon2/fan2");
+        assert_eq!((fan.chip.as_str(), fan.label.as_str()), ("hwmon2", "fan2"));
+        assert_eq!(fan.critical, None);
+        assert_eq!(fan.history.latest(), Some(812.0));
+
+        assert_eq!(find(&snapshot, "hwmon2/in0").history.latest(), Some(1.048));
+        assert_eq!(find(&snapshot, "hwmon2/curr1").history.latest(), Some(1.5));
+        assert_eq!(find(&snapshot, "hwmon2/power1").history.latest(), Some(5.0));
+        assert_eq!(find(&snapshot, "hwmon2/power2").history.latest(), Some(3.5));
+    }
+
+    #[test]
+    fn empty_tree_disables_the_source() {
+        let hwmon = TempTree::new();
+        let mut source = SensorSource::new(hwmon.path());
+        assert!(source.init(&mut Snapshot::default(), &Settings::new(200, 10)).is_err());
+    }
+}
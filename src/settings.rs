@@ -0,0 +1,191 @@
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::time::Duration;
+use crate::cgroups::{CgroupFocus, DEFAULT_CGROUP_ROOT};
+use crate::sensors::DEFAULT_HWMON_ROOT;
+
+pub (crate) const DEFAULT_INTERVAL_MS: u64 = 200;
+pub (crate) const DEFAULT_HISTORY_SIZE: usize = 120; // number of samples per series
+pub (crate) const MIN_INTERVAL_MS: u64 = 50;
+pub (crate) const MIN_HISTORY_SIZE: usize = 2;
+pub (crate) const MAX_HISTORY_SIZE: usize = 3600;
+
+// Sampling settings shared between the UI and the sampler threads, which re-read them every tick
+#[derive(Debug)]
+pub (crate) struct Settings {
+    interval_ms: AtomicU64,
+    history_size: AtomicUsize,
+    // Groups the cgroup source reads, or None for all of them
+    cgroup_focus: Mutex<Option<CgroupFocus>>,
+}
+
+impl Settings {
+    pub (crate) fn new(interval_ms: u64, history_size: usize) -> Self {
+        Self {
+            interval_ms: AtomicU64::new(interval_ms.max(MIN_INTERVAL_MS)),
+            history_size: AtomicUsize::new(history_size.clamp(MIN_HISTORY_SIZE, MAX_HISTORY_SIZE)),
+            cgroup_focus: Mutex::new(None),
+        }
+    }
+
+    pub (crate) fn interval(&self) -> Duration {
+        Duration::from_millis(self.interval_ms())
+    }
+
+    pub (crate) fn interval_ms(&self) -> u64 {
+        self.interval_ms.load(Ordering::Relaxed)
+    }
+
+    pub (crate) fn set_interval_ms(&self, interval_ms: u64) {
+        self.interval_ms.store(interval_ms.max(MIN_INTERVAL_MS), Ordering::Relaxed);
+    }
+
+    pub (crate) fn history_size(&self) -> usize {
+        self.history_size.load(Ordering::Relaxed)
+    }
+
+    pub (crate) fn set_history_size(&self, history_size: usize) {
+        self.history_size.store(history_size.clamp(MIN_HISTORY_SIZE, MAX_HISTORY_SIZE), Ordering::Relaxed);
+    }
+
+    pub (crate) fn cgroup_focus(&self) -> Option<CgroupFocus> {
+        self.cgroup_focus.lock().unwrap_or_else(|poisoned| poisoned.into_inner()).clone()
+    }
+
+    pub (crate) fn set_cgroup_focus(&self, focus: CgroupFocus) {
+        *self.cgroup_focus.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = Some(focus);
+    }
+}
+
+// Command line options
+#[derive(Debug)]
+pub (crate) struct Options {
+    pub (crate) interval_ms: u64,
+    pub (crate) history_size: usize,
+    pub (crate) fake_gpu: bool,
+    pub (crate) list_series: bool,
+    pub (crate) hwmon_root: PathBuf,
+    pub (crate) cgroup_root: PathBuf,
+    // Group whose limits the container-aware mode uses, instead of the monitor's own
+    pub (crate) limits_cgroup: Option<String>,
+    pub (crate) container_limits: bool,
+}
+
+pub (crate) const USAGE: &str = "usage: rust-system-monitor [--interval-ms <ms>] [--history <samples>] [--fake-gpu] [--list-series] [--hwmon-root <dir>] [--cgroup-root <dir>] [--limits-cgroup <path>] [--container-limits]";
+
+impl Options {
+    pub (crate) fn parse(mut args: impl Iterator<Item = String>) -> Result<Self, String> {
+        let mut options = Options {
+            interval_ms: DEFAULT_INTERVAL_MS,
+            history_size: DEFAULT_HISTORY_SIZE,
+            fake_gpu: false,
+            list_series: false,
+            hwmon_root: PathBuf::from(DEFAULT_HWMON_ROOT),
+            cgroup_root: PathBuf::from(DEFAULT_CGROUP_ROOT),
+            limits_cgroup: None,
+            container_limits: false,
+        };
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--interval-ms" => options.interval_ms = Self::value(&arg, args.next())?,
+                "--history" => {
+                    options.history_size = Self::value(&arg, args.next())?;
+                    if options.history_size > MAX_HISTORY_SIZE {
+                        return Err(format!("`{}` is at most {} samples\n{}", arg, MAX_HISTORY_SIZE, USAGE));
+                    }
+                }
+                // Replays scripted readings so the GPU panels can be exercised without hardware
+                "--fake-gpu" => options.fake_gpu = true,
+                "--list-series" => options.list_series = true,
+                // Lets the sensors panel be pointed at a fake sysfs tree
+                "--hwmon-root" => options.hwmon_root = Self::value(&arg, args.next())?,
+                "--cgroup-root" => options.cgroup_root = Self::value(&arg, args.next())?,
+                "--limits-cgroup" => options.limits_cgroup = Some(Self::value(&arg, args.next())?),
+                "--container-limits" => options.container_limits = true,
+                _ => return Err(format!("unknown option `{}`\n{}", arg, USAGE)),
+            }
+        }
+        Ok(options)
+    }
+
+    fn value<T: std::str::FromStr>(option: &str, value: Option<String>) -> Result<T, String> {
+        let value = value.ok_or_else(|| format!("`{}` needs a value\n{}", option, USAGE))?;
+        value.parse().map_err(|_| format!("invalid value `{}` for `{}`\n{}", value, option, USAGE))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Options, String> {
+        Options::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    // The first line of an error, without the usage text after it
+    fn error(args: &[&str]) -> String {
+        let err = parse(args).unwrap_err();
+        assert!(err.ends_with(USAGE), "{}", err);
+        err.lines().next().unwrap_or_default().to_string()
+    }
+
+    #[test]
+    fn defaults_without_arguments() {
+        let options = parse(&[]).unwrap();
+        assert_eq!(options.interval_ms, DEFAULT_INTERVAL_MS);
+        assert_eq!(options.history_size, DEFAULT_HISTORY_SIZE);
+        assert!(!options.fake_gpu);
+        assert!(!options.list_series);
+        assert_eq!(options.hwmon_root, PathBuf::from(DEFAULT_HWMON_ROOT));
+        assert_eq!(options.cgroup_root, PathBuf::from(DEFAULT_CGROUP_ROOT));
+        assert_eq!(options.limits_cgroup, None);
+        assert!(!options.container_limits);
+    }
+
+    #[test]
+    fn parses_every_option() {
+        let options = parse(&[
+            "--interval-ms", "500",
+            "--history", "300",
+            "--fake-gpu",
+            "--list-series",
+            "--hwmon-root", "/tmp/hwmon",
+            "--cgroup-root", "/tmp/cgroup",
+            "--limits-cgroup", "/system.slice/app.service",
+            "--container-limits",
+        ]).unwrap();
+        assert_eq!(options.interval_ms, 500);
+        assert_eq!(options.history_size, 300);
+        assert!(options.fake_gpu);
+        assert!(options.list_series);
+        assert_eq!(options.hwmon_root, PathBuf::from("/tmp/hwmon"));
+        assert_eq!(options.cgroup_root, PathBuf::from("/tmp/cgroup"));
+        assert_eq!(options.limits_cgroup.as_deref(), Some("/system.slice/app.service"));
+        assert!(options.container_limits);
+    }
+
+    #[test]
+    fn rejects_bad_arguments() {
+        assert_eq!(error(&["--interval-ms"]), "`--interval-ms` needs a value");
+        assert_eq!(error(&["--fake-gpu", "--hwmon-root"]), "`--hwmon-root` needs a value");
+        assert_eq!(error(&["--interval-ms", "fast"]), "invalid value `fast` for `--interval-ms`");
+        assert_eq!(error(&["--history", "-1"]), "invalid value `-1` for `--history`");
+        assert_eq!(error(&["--verbose"]), "unknown option `--verbose`");
+        // A value is only taken after an option that needs one
+        assert_eq!(error(&["--fake-gpu", "500"]), "unknown option `500`");
+    }
+
+    #[test]
+    fn bounds_the_history_size() {
+        assert_eq!(parse(&["--history", "3600"]).unwrap().history_size, MAX_HISTORY_SIZE);
+        assert_eq!(error(&["--history", "3601"]), "`--history` is at most 3600 samples");
+
+        let settings = Settings::new(0, 0);
+        assert_eq!(settings.interval_ms(), MIN_INTERVAL_MS);
+        assert_eq!(settings.history_size(), MIN_HISTORY_SIZE);
+        settings.set_history_size(usize::MAX);
+        assert_eq!(settings.history_size(), MAX_HISTORY_SIZE);
+    }
+}
@@ -0,0 +1,88 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+// Helpers for the small text files exposed under /sys and /proc
+
+pub (crate) fn read_string(path: impl AsRef<Path>) -> Option<String> {
+    fs::read_to_string(path).ok().map(|s| s.trim().to_string())
+}
+
+pub (crate) fn read_u64(path: impl AsRef<Path>) -> Option<u64> {
+    read_string(path)?.parse().ok()
+}
+
+pub (crate) fn read_f64(path: impl AsRef<Path>) -> Option<f64> {
+    read_string(path)?.parse().ok()
+}
+
+// Entries of `dir` whose file name starts with `prefix`, sorted by the number following it
+// (so `fan10_input` comes after `fan2_input`)
+pub (crate) fn numbered_entries(dir: impl AsRef<Path>, prefix: &str, suffix: &str) -> Vec<(u32, PathBuf)> {
+    let Ok(entries) = fs::read_dir(dir) else { return Vec::new() };
+    let mut found: Vec<(u32, PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+            let number = name.strip_prefix(prefix)?.strip_suffix(suffix)?.parse().ok()?;
+            Some((number, entry.path()))
+        })
+        .collect();
+    found.sort_by_key(|(number, _)| *number);
+    found
+}
+
+// The first hwmon node of a device directory (device/hwmon/hwmonN)
+pub (crate) fn hwmon_dir(device: impl AsRef<Path>) -> Option<PathBuf> {
+    numbered_entries(device.as_ref().join("hwmon"), "hwmon", "")
+        .into_iter()
+        .map(|(_, hwmon)| hwmon)
+        .next()
+}
+
+// Scratch directory holding a fake /sys or /proc tree for tests, removed when dropped
+#[cfg(test)]
+pub (crate) struct TempTree {
+    root: PathBuf,
+}
+
+#[cfg(test)]
+impl TempTree {
+    pub (crate) fn new() -> Self {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let root = std::env::temp_dir().join(format!(
+            "rust-system-monitor-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&root).expect("create temp dir");
+        Self { root }
+    }
+
+    pub (crate) fn path(&self) -> &Path {
+        &self.root
+    }
+
+    // Write `contents` to `path` below the root, creating its parent directories
+    pub (crate) fn write(&self, path: &str, contents: &str) -> &Self {
+        let path = self.root.join(path);
+        fs::create_dir_all(path.parent().expect("file below the root")).expect("create parent dirs");
+        fs::write(path, contents).expect("write file");
+        self
+    }
+
+    // Link `path` below the root to `target`, which is left as given and need not exist
+    pub (crate) fn symlink(&self, path: &str, target: &str) -> &Self {
+        let path = self.root.join(path);
+        fs::create_dir_all(path.parent().expect("link below the root")).expect("create parent dirs");
+        std::os::unix::fs::symlink(target, path).expect("create symlink");
+        self
+    }
+}
+
+#[cfg(test)]
+impl Drop for TempTree {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
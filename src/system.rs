@@ -1,89 +1,514 @@
-use std::thread;
-use std::time::Duration;
-use druid::{ExtEventSink, Lens, Target};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use druid::Lens;
 use im::Vector;
 use sysinfo::System;
-use crate::{gpu, State, HISTORY_SIZE, UPDATE_GPU, UPDATE_METRICS};
+use crate::history::TimeSeries;
+use crate::sampler::{MetricSource, SeriesInfo, Snapshot, SourceError};
+use crate::settings::Settings;
+use crate::sysfs;
 
-#[derive(Clone, Lens, Debug)]
+#[derive(Clone, Lens, Debug, Default)]
 pub (crate) struct SystemStats {
-    pub (crate) cpu_history: Vector<Vector<f64>>,
-    pub (crate) cpu_avg_history: Vector<f64>,
-    pub (crate) used_mem_history: Vector<f64>,
+    pub (crate) cpu_history: Vector<TimeSeries>,
+    pub (crate) cpu_avg_history: TimeSeries,
+    pub (crate) used_mem_history: TimeSeries,
     pub (crate)used_mem: f64,
     pub (crate) total_mem: f64,
+    pub (crate) used_swap_history: TimeSeries,
+    // Bytes/s paged in from and out to swap
+    pub (crate) swap_in_history: TimeSeries,
+    pub (crate) swap_out_history: TimeSeries,
+    pub (crate) used_swap: f64,
+    pub (crate) total_swap: f64,
+    // Bytes in each of MEMORY_CATEGORIES, in the same order
+    pub (crate) memory_breakdown: Vector<TimeSeries>,
+    // Per-core clock in MHz, and the range cpufreq allows (0 when cpufreq isn't available)
+    pub (crate) cpu_freq_history: Vector<TimeSeries>,
+    pub (crate) cpu_freq_min: f64,
+    pub (crate) cpu_freq_max: f64,
+    // Percentage of CPU time spent in each of CPU_STATES, across all cores and per core
+    pub (crate) cpu_time_breakdown: Vector<TimeSeries>,
+    pub (crate) core_time_breakdown: Vector<Vector<TimeSeries>>,
+    pub (crate) host: HostInfo,
+    // Seconds since boot
+    pub (crate) uptime: u64,
+    pub (crate) load: LoadAverage,
+}
+
+// Facts about the machine that don't change while running
+#[derive(Clone, Debug, Default)]
+pub (crate) struct HostInfo {
+    pub (crate) host_name: String,
+    pub (crate) os_version: String,
+    pub (crate) kernel_version: String,
+    pub (crate) cpu_model: String,
+    pub (crate) physical_cores: Option<usize>,
+    pub (crate) logical_cores: usize,
+}
+
+// 1, 5 and 15 minute load averages and the scheduler's task counts, from /proc/loadavg
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub (crate) struct LoadAverage {
+    pub (crate) one: f64,
+    pub (crate) five: f64,
+    pub (crate) fifteen: f64,
+    pub (crate) running_tasks: u64,
+    pub (crate) total_tasks: u64,
 }
 
 impl SystemStats {
-    pub(crate) fn new(sink: ExtEventSink) -> Self {
-        let mut sys = System::new_all();
-        sys.refresh_cpu_all();
+    fn set_capacity(&mut self, capacity: usize) {
+        for core_history in self.cpu_history.iter_mut() {
+            core_history.set_capacity(capacity);
+        }
+        self.cpu_avg_history.set_capacity(capacity);
+        self.used_mem_history.set_capacity(capacity);
+        self.used_swap_history.set_capacity(capacity);
+        self.swap_in_history.set_capacity(capacity);
+        self.swap_out_history.set_capacity(capacity);
+        for category in self.memory_breakdown.iter_mut() {
+            category.set_capacity(capacity);
+        }
+        for core_history in self.cpu_freq_history.iter_mut() {
+            core_history.set_capacity(capacity);
+        }
+        for state in self.cpu_time_breakdown.iter_mut() {
+            state.set_capacity(capacity);
+        }
+        for core in self.core_time_breakdown.iter_mut() {
+            for state in core.iter_mut() {
+                state.set_capacity(capacity);
+            }
+        }
+    }
+}
 
-        let cores = sys.cpus().len();
-        let mut initial_history = Vector::new();
-        for _ in 0..cores {
-            initial_history.push_back(Vector::from(vec![0.0; HISTORY_SIZE]));
+// Categories of the memory breakdown, from the bottom of the stacked chart up. Together they add
+// up to MemTotal.
+pub (crate) const MEMORY_CATEGORIES: [&str; 9] = [
+    "Anon",
+    "Page cache",
+    "Buffers",
+    "Shmem",
+    "Slab",
+    "Kernel stack",
+    "Page tables",
+    "Other",
+    "Free",
+];
+
+// States of the CPU time breakdown, from the bottom of the stacked chart up. Idle time is
+// whatever is left above them.
+pub (crate) const CPU_STATES: [&str; 7] = [
+    "User",
+    "Nice",
+    "System",
+    "IRQ",
+    "SoftIRQ",
+    "IOWait",
+    "Steal",
+];
+
+// Cumulative time of one /proc/stat cpu line, in clock ticks: the CPU_STATES followed by idle.
+// Guest time is already counted in user and nice, so it's left out.
+#[derive(Clone, Copy, Debug, Default)]
+struct CpuTimes([u64; 8]);
+
+impl CpuTimes {
+    // Percentage of the time between two readings spent in each of CPU_STATES
+    fn breakdown(&self, last: &CpuTimes) -> Option<[f64; 7]> {
+        let delta: Vec<f64> = self.0.iter().zip(last.0).map(|(current, last)| current.saturating_sub(last) as f64).collect();
+        let total: f64 = delta.iter().sum();
+        if total <= 0.0 {
+            return None;
+        }
+        let mut states = [0.0; 7];
+        for (state, time) in states.iter_mut().zip(&delta) {
+            *state = time / total * 100.0;
         }
+        Some(states)
+    }
+}
+
+// The aggregate "cpu" line of /proc/stat and the "cpuN" lines, keyed by N. Offline cores have no line.
+fn parse_stat(text: &str) -> (Option<CpuTimes>, HashMap<usize, CpuTimes>) {
+    let mut total = None;
+    let mut cores = HashMap::new();
+    for line in text.lines() {
+        let mut fields = line.split_whitespace();
+        let Some(name) = fields.next().and_then(|name| name.strip_prefix("cpu")) else { continue };
+        // user nice system idle iowait irq softirq steal
+        let values: Vec<u64> = fields.take(8).filter_map(|field| field.parse().ok()).collect();
+        let [user, nice, system, idle, iowait, irq, softirq, steal] = values[..] else { continue };
+        let times = CpuTimes([user, nice, system, irq, softirq, iowait, steal, idle]);
+        if name.is_empty() {
+            total = Some(times);
+        } else if let Ok(core) = name.parse() {
+            cores.insert(core, times);
+        }
+    }
+    (total, cores)
+}
+
+// Split /proc/meminfo into MEMORY_CATEGORIES, in bytes
+fn parse_meminfo(text: &str) -> Option<[f64; 9]> {
+    let mut fields = HashMap::new();
+    for line in text.lines() {
+        let Some((key, value)) = line.split_once(':') else { continue };
+        // Values are in kB apart from the HugePages_ counts, which aren't used here
+        let Some(kb) = value.split_whitespace().next().and_then(|kb| kb.parse::<f64>().ok()) else { continue };
+        fields.insert(key, kb * 1024.0);
+    }
+    let field = |key: &str| fields.get(key).copied().unwrap_or(0.0);
+
+    let total = *fields.get("MemTotal")?;
+    let shmem = field("Shmem");
+    // Cached counts shmem (tmpfs, shared anonymous mappings) as page cache, so take it out
+    let cache = (field("Cached") - shmem).max(0.0);
+    let mut categories = [
+        field("AnonPages"),
+        cache,
+        field("Buffers"),
+        shmem,
+        field("Slab"),
+        field("KernelStack"),
+        field("PageTables"),
+        0.0,
+        field("MemFree"),
+    ];
+    // Whatever is left: huge pages, vmalloc, driver allocations and so on
+    categories[7] = (total - categories.iter().sum::<f64>()).max(0.0);
+    Some(categories)
+}
+
+// e.g. "0.52 0.58 0.59 2/1234 56789"; the last field, the most recent PID, is ignored
+fn parse_loadavg(text: &str) -> Option<LoadAverage> {
+    let mut fields = text.split_whitespace();
+    let mut average = || fields.next()?.parse::<f64>().ok();
+    let (one, five, fifteen) = (average()?, average()?, average()?);
+    let (running, total) = fields.next()?.split_once('/')?;
+    Some(LoadAverage {
+        one,
+        five,
+        fifteen,
+        running_tasks: running.parse().ok()?,
+        total_tasks: total.parse().ok()?,
+    })
+}
+
+// Pages swapped in and out since boot, from /proc/vmstat
+#[derive(Clone, Copy, Debug, Default)]
+struct SwapCounters {
+    pswpin: u64,
+    pswpout: u64,
+}
+
+fn parse_vmstat(text: &str) -> SwapCounters {
+    let mut counters = SwapCounters::default();
+    for line in text.lines() {
+        let Some((key, value)) = line.split_once(' ') else { continue };
+        let Ok(value) = value.trim().parse() else { continue };
+        match key {
+            "pswpin" => counters.pswpin = value,
+            "pswpout" => counters.pswpout = value,
+            _ => {}
+        }
+    }
+    counters
+}
+
+// CPU, RAM and swap usage from sysinfo, with swap activity and the memory breakdown from
+// <proc_root> and frequency limits from <sys_root>
+pub (crate) struct SystemSource {
+    sys: System,
+    proc_root: PathBuf,
+    sys_root: PathBuf,
+    page_size: f64,
+    last_swap: Option<(Instant, SwapCounters)>,
+    last_cpu_times: (Option<CpuTimes>, HashMap<usize, CpuTimes>),
+}
+
+impl SystemSource {
+    pub (crate) fn new(proc_root: impl AsRef<Path>, sys_root: impl AsRef<Path>) -> Self {
+        // SAFETY: sysconf only reads its argument
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) };
+        Self {
+            sys: System::new_all(),
+            proc_root: proc_root.as_ref().to_path_buf(),
+            sys_root: sys_root.as_ref().to_path_buf(),
+            page_size: if page_size > 0 { page_size as f64 } else { 4096.0 },
+            last_swap: None,
+            last_cpu_times: (None, HashMap::new()),
+        }
+    }
+
+    // Lowest and highest clock any core can run at, in MHz
+    fn frequency_range(&self) -> Option<(f64, f64)> {
+        let cpus = sysfs::numbered_entries(self.sys_root.join("devices/system/cpu"), "cpu", "");
+        let limits: Vec<(f64, f64)> = cpus.iter()
+            .filter_map(|(_, cpu)| {
+                let min = sysfs::read_f64(cpu.join("cpufreq/cpuinfo_min_freq"))?;
+                let max = sysfs::read_f64(cpu.join("cpufreq/cpuinfo_max_freq"))?;
+                // cpufreq reports kHz
+                Some((min / 1000.0, max / 1000.0))
+            })
+            .collect();
+        let min = limits.iter().map(|(min, _)| *min).reduce(f64::min)?;
+        let max = limits.iter().map(|(_, max)| *max).reduce(f64::max)?;
+        Some((min, max))
+    }
+
+    fn read_memory_breakdown(&self) -> Option<[f64; 9]> {
+        fs::read_to_string(self.proc_root.join("meminfo")).ok().and_then(|text| parse_meminfo(&text))
+    }
+
+    fn read_load_average(&self) -> Option<LoadAverage> {
+        fs::read_to_string(self.proc_root.join("loadavg")).ok().and_then(|text| parse_loadavg(&text))
+    }
+
+    fn host_info(&self) -> HostInfo {
+        HostInfo {
+            host_name: System::host_name().unwrap_or_default(),
+            os_version: System::long_os_version().unwrap_or_default(),
+            kernel_version: System::kernel_version().unwrap_or_default(),
+            // Every core of a machine reports the same model, so take the first
+            cpu_model: self.sys.cpus().first().map(|cpu| cpu.brand().trim().to_string()).unwrap_or_default(),
+            physical_cores: System::physical_core_count(),
+            logical_cores: self.sys.cpus().len(),
+        }
+    }
+
+    fn read_cpu_times(&self) -> (Option<CpuTimes>, HashMap<usize, CpuTimes>) {
+        fs::read_to_string(self.proc_root.join("stat")).map(|text| parse_stat(&text)).unwrap_or_default()
+    }
 
-        // Set initial state
-        let state = SystemStats {
-            cpu_history: initial_history,
-            cpu_avg_history: Vector::from(vec![0.0; HISTORY_SIZE]),
-            used_mem_history: Vector::from(vec![0.0; HISTORY_SIZE]),
+    fn read_swap_counters(&self) -> Option<SwapCounters> {
+        fs::read_to_string(self.proc_root.join("vmstat")).ok().map(|text| parse_vmstat(&text))
+    }
+}
+
+impl MetricSource for SystemSource {
+    fn name(&self) -> &'static str {
+        "system"
+    }
+
+    fn init(&mut self, snapshot: &mut Snapshot, settings: &Settings) -> Result<(), SourceError> {
+        self.sys.refresh_cpu_all();
+
+        let history_size = settings.history_size();
+        snapshot.system = SystemStats {
+            cpu_history: self.sys.cpus().iter().map(|_| TimeSeries::new(history_size)).collect(),
+            cpu_avg_history: TimeSeries::new(history_size),
+            used_mem_history: TimeSeries::new(history_size),
             used_mem: 0.0,
             total_mem: 0.0,
+            used_swap_history: TimeSeries::new(history_size),
+            swap_in_history: TimeSeries::new(history_size),
+            swap_out_history: TimeSeries::new(history_size),
+            used_swap: 0.0,
+            total_swap: 0.0,
+            memory_breakdown: MEMORY_CATEGORIES.iter().map(|_| TimeSeries::new(history_size)).collect(),
+            cpu_freq_history: self.sys.cpus().iter().map(|_| TimeSeries::new(history_size)).collect(),
+            cpu_freq_min: 0.0,
+            cpu_freq_max: 0.0,
+            cpu_time_breakdown: CPU_STATES.iter().map(|_| TimeSeries::new(history_size)).collect(),
+            core_time_breakdown: self.sys.cpus().iter()
+                .map(|_| CPU_STATES.iter().map(|_| TimeSeries::new(history_size)).collect())
+                .collect(),
+            host: self.host_info(),
+            uptime: System::uptime(),
+            load: self.read_load_average().unwrap_or_default(),
         };
+        if let Some((min, max)) = self.frequency_range() {
+            snapshot.system.cpu_freq_min = min;
+            snapshot.system.cpu_freq_max = max;
+        }
+        self.last_swap = self.read_swap_counters().map(|counters| (Instant::now(), counters));
+        self.last_cpu_times = self.read_cpu_times();
+        Ok(())
+    }
+
+    fn sample(&mut self, snapshot: &mut Snapshot, settings: &Settings) -> Result<(), SourceError> {
+        let stats = &mut snapshot.system;
+        let sys = &mut self.sys;
+        // The history length can be changed from the UI while running
+        stats.set_capacity(settings.history_size());
+
+        sys.refresh_cpu_all();
+        sys.refresh_memory();
+
+        let mut total_cpu_usage = 0.0;
+
+        for (i, cpu) in sys.cpus().iter().enumerate() {
+            stats.cpu_history[i].push(cpu.cpu_usage() as f64);
+            stats.cpu_freq_history[i].push(cpu.frequency() as f64);
+            total_cpu_usage += cpu.cpu_usage() as f64;
+        }
+
+        let mem_usage = (sys.used_memory() as f64 / sys.total_memory() as f64) * 100.0;
+        stats.used_mem_history.push(mem_usage);
+
+        let avg_cpu_usage = total_cpu_usage / sys.cpus().len() as f64;
+        stats.cpu_avg_history.push(avg_cpu_usage);
+
+        stats.used_mem = sys.used_memory() as f64 / 1024.0 / 1024.0;
+        stats.total_mem = sys.total_memory() as f64 / 1024.0 / 1024.0;
+
+        // A machine without swap reports zero total; show that as 0% used
+        let swap_usage = if sys.total_swap() > 0 {
+            sys.used_swap() as f64 / sys.total_swap() as f64 * 100.0
+        } else {
+            0.0
+        };
+        stats.used_swap_history.push(swap_usage);
+        stats.used_swap = sys.used_swap() as f64 / 1024.0 / 1024.0;
+        stats.total_swap = sys.total_swap() as f64 / 1024.0 / 1024.0;
+
+        if let Some(breakdown) = self.read_memory_breakdown() {
+            for (history, bytes) in stats.memory_breakdown.iter_mut().zip(breakdown) {
+                history.push(bytes);
+            }
+        }
+
+        let now = Instant::now();
+        let counters = self.read_swap_counters();
+        if let (Some((last_time, last)), Some(current)) = (self.last_swap, counters) {
+            let elapsed = now.duration_since(last_time).as_secs_f64();
+            if elapsed > 0.0 {
+                let rate = |current: u64, last: u64| current.saturating_sub(last) as f64 * self.page_size / elapsed;
+                stats.swap_in_history.push(rate(current.pswpin, last.pswpin));
+                stats.swap_out_history.push(rate(current.pswpout, last.pswpout));
+            }
+        }
+        self.last_swap = counters.map(|counters| (now, counters));
 
-        thread::spawn(move || {
-            let mut sys = System::new_all();
-            let mut history = vec![vec![0.0; HISTORY_SIZE]; cores];
-            let mut avg_history = vec![0.0; HISTORY_SIZE];
-            let mut mem_history = vec![0.0; HISTORY_SIZE];
-
-            loop {
-                sys.refresh_cpu_all();
-                sys.refresh_memory();
-
-                let mut total_cpu_usage = 0.0;
-
-                for (i, cpu) in sys.cpus().iter().enumerate() {
-                    history[i].rotate_left(1);
-                    history[i][HISTORY_SIZE - 1] = cpu.cpu_usage() as f64;
-                    total_cpu_usage += cpu.cpu_usage() as f64;
-                }
-
-                let mem_usage = (sys.used_memory() as f64 / sys.total_memory() as f64) * 100.0;
-                mem_history.rotate_left(1);
-                mem_history[HISTORY_SIZE - 1] = mem_usage.clone() as f64;
-
-                let avg_cpu_usage = total_cpu_usage / cores as f64;
-                avg_history.rotate_left(1);
-                avg_history[HISTORY_SIZE - 1] = avg_cpu_usage.clone();
-
-                let used_mem = sys.used_memory() as f64 / 1024.0 / 1024.0;
-                let total_mem = sys.total_memory() as f64 / 1024.0 / 1024.0;
-
-                // convert Vec<Vec<f64>> -> im::Vector<im::Vector<f64>>
-                let mut history_vector = Vector::new();
-                for v in &history {
-                    history_vector.push_back(Vector::from(v.clone()));
-                }
-
-                let updated_sys = SystemStats {
-                    cpu_history: history_vector.clone(),
-                    cpu_avg_history: Vector::from(avg_history.clone()),
-                    used_mem_history: Vector::from(mem_history.clone()),
-                    used_mem,
-                    total_mem,
-                };
-
-                sink.submit_command(UPDATE_METRICS, updated_sys, Target::Auto)
-                    .unwrap();
-
-                thread::sleep(Duration::from_millis(200));
+        let (total, cores) = self.read_cpu_times();
+        let (last_total, last_cores) = &self.last_cpu_times;
+        if let (Some(total), Some(last)) = (total, last_total) && let Some(breakdown) = total.breakdown(last) {
+            for (history, percent) in stats.cpu_time_breakdown.iter_mut().zip(breakdown) {
+                history.push(percent);
             }
-        });
+        }
+        for (core, histories) in stats.core_time_breakdown.iter_mut().enumerate() {
+            let (Some(times), Some(last)) = (cores.get(&core), last_cores.get(&core)) else { continue };
+            let Some(breakdown) = times.breakdown(last) else { continue };
+            for (history, percent) in histories.iter_mut().zip(breakdown) {
+                history.push(percent);
+            }
+        }
+        self.last_cpu_times = (total, cores);
+
+        stats.uptime = System::uptime();
+        if let Some(load) = self.read_load_average() {
+            stats.load = load;
+        }
+        Ok(())
+    }
+
+    fn series(&self) -> Vec<SeriesInfo> {
+        let mut series = vec![SeriesInfo::new("cpu.average", "%")];
+        series.extend((0..self.sys.cpus().len()).map(|i| SeriesInfo::new(format!("cpu.core{}", i + 1), "%")));
+        series.extend((0..self.sys.cpus().len()).map(|i| SeriesInfo::new(format!("cpu.core{}.frequency", i + 1), "MHz")));
+        series.extend(CPU_STATES.iter().map(|state| SeriesInfo::new(format!("cpu.{}", state.to_lowercase()), "%")));
+        for i in 0..self.sys.cpus().len() {
+            series.extend(CPU_STATES.iter().map(|state| SeriesInfo::new(format!("cpu.core{}.{}", i + 1, state.to_lowercase()), "%")));
+        }
+        series.push(SeriesInfo::new("memory.used", "%"));
+        series.extend(MEMORY_CATEGORIES.iter().map(|category| {
+            SeriesInfo::new(format!("memory.{}", category.to_lowercase().replace(' ', "_")), "B")
+        }));
+        series.push(SeriesInfo::new("swap.used", "%"));
+        series.push(SeriesInfo::new("swap.in", "B/s"));
+        series.push(SeriesInfo::new("swap.out", "B/s"));
+        series
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_vmstat() {
+        let text = "nr_free_pages 3022437\nnr_zone_inactive_anon 102931\npgpgin 5838302\npgpgout 10745116\n\
+            pswpin 1532\npswpout 40217\npgalloc_dma 1024\n";
+        let counters = parse_vmstat(text);
+        assert_eq!((counters.pswpin, counters.pswpout), (1532, 40217));
+
+        // Kernels without swap support leave the counters out
+        let counters = parse_vmstat("nr_free_pages 3022437\npgpgin 5838302\n");
+        assert_eq!((counters.pswpin, counters.pswpout), (0, 0));
+    }
+
+    #[test]
+    fn parses_meminfo() {
+        let text = "MemTotal:       16303656 kB\nMemFree:         8410036 kB\nMemAvailable:   12109632 kB\n\
+            Buffers:          245448 kB\nCached:          3765940 kB\nSwapCached:            0 kB\n\
+            Active:          4123456 kB\nAnonPages:       2847720 kB\nMapped:           812344 kB\n\
+            Shmem:            512344 kB\nKReclaimable:     287048 kB\nSlab:             412680 kB\n\
+            SReclaimable:     287048 kB\nKernelStack:       18928 kB\nPageTables:        45312 kB\n\
+            HugePages_Total:       0\nHugepagesize:       2048 kB\n";
+        let kb = |kb: f64| kb * 1024.0;
+        let categories = parse_meminfo(text).unwrap();
+
+        assert_eq!(categories, [
+            kb(2847720.0),
+            // Cached minus Shmem
+            kb(3253596.0),
+            kb(245448.0),
+            kb(512344.0),
+            kb(412680.0),
+            kb(18928.0),
+            kb(45312.0),
+            // MemTotal minus everything else
+            kb(557592.0),
+            kb(8410036.0),
+        ]);
+        assert_eq!(categories.iter().sum::<f64>(), kb(16303656.0));
+    }
+
+    #[test]
+    fn meminfo_needs_a_total() {
+        assert!(parse_meminfo("MemFree:         8410036 kB\n").is_none());
+    }
+
+    #[test]
+    fn parses_loadavg() {
+        let load = parse_loadavg("0.52 0.58 0.59 2/1134 12345\n").unwrap();
+        assert_eq!((load.one, load.five, load.fifteen), (0.52, 0.58, 0.59));
+        assert_eq!((load.running_tasks, load.total_tasks), (2, 1134));
+
+        assert!(parse_loadavg("0.52 0.58 0.59").is_none());
+        assert!(parse_loadavg("0.52 0.58 0.59 2/").is_none());
+        assert!(parse_loadavg("").is_none());
+    }
+
+    #[test]
+    fn parses_stat() {
+        let text = "cpu  2255 34 2290 22625563 6290 127 456 0 0 0\n\
+            cpu0 1132 34 1441 11311718 3675 127 438 0 0 0\n\
+            cpu2 1123 0 849 11313845 2614 0 18 0 0 0\n\
+            intr 114930548 113199788 3 0 5 263 0 4 [...]\n\
+            ctxt 1990473\nbtime 1062191376\nprocesses 2915\nprocs_running 1\nprocs_blocked 0\n";
+        let (total, cores) = parse_stat(text);
+
+        // Reordered to CPU_STATES, followed by idle
+        assert_eq!(total.unwrap().0, [2255, 34, 2290, 127, 456, 6290, 0, 22625563]);
+        // cpu1 is offline, so it has no line
+        assert_eq!(cores.len(), 2);
+        assert_eq!(cores[&0].0, [1132, 34, 1441, 127, 438, 3675, 0, 11311718]);
+        assert_eq!(cores[&2].0, [1123, 0, 849, 0, 18, 2614, 0, 11313845]);
+    }
 
-        state
+    #[test]
+    fn breaks_cpu_time_down_by_state() {
+        let last = CpuTimes([100, 0, 50, 0, 0, 10, 0, 1000]);
+        let current = CpuTimes([150, 0, 75, 5, 0, 20, 0, 1110]);
+        // 200 ticks passed: 50 user, 25 system, 5 IRQ, 10 iowait and 110 idle
+        assert_eq!(current.breakdown(&last), Some([25.0, 0.0, 12.5, 2.5, 0.0, 5.0, 0.0]));
+        assert_eq!(last.breakdown(&last), None);
     }
 }
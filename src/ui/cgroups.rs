@@ -0,0 +1,210 @@
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use druid::{Color, Data, Env, Lens, RenderContext, Widget, WidgetExt};
+use druid::widget::{Button, CrossAxisAlignment, Flex, Label, List, Painter, Scroll};
+use crate::cgroups::Cgroup;
+use crate::{State, SELECT_CGROUP, TOGGLE_CGROUP};
+use crate::ui::table::{cell, compare_f64, header, TableSort};
+use crate::ui::usage_graph::{format_bytes, PlotType, UsageGraph};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum CgroupColumn {
+    Name,
+    Cpu,
+    Memory,
+    Limit,
+    Read,
+    Write,
+    Tasks,
+}
+
+const COLUMNS: [(&str, CgroupColumn, f64); 7] = [
+    ("Group", CgroupColumn::Name, 320.0),
+    ("CPU %", CgroupColumn::Cpu, 70.0),
+    ("Memory", CgroupColumn::Memory, 90.0),
+    ("Limit", CgroupColumn::Limit, 90.0),
+    ("Read/s", CgroupColumn::Read, 90.0),
+    ("Write/s", CgroupColumn::Write, 90.0),
+    ("Tasks", CgroupColumn::Tasks, 70.0),
+];
+
+const TOGGLE_WIDTH: f64 = 20.0;
+
+#[derive(Clone, Data, Lens, Debug)]
+pub(crate) struct CgroupRow {
+    path: String,
+    name: String,
+    depth: usize,
+    // Whether the children are listed, or None for a group without children
+    expanded: Option<bool>,
+    selected: bool,
+    cpu: f64,
+    memory: Option<f64>,
+    memory_max: Option<f64>,
+    read: f64,
+    write: f64,
+    pids: Option<u64>,
+}
+
+fn compare(column: CgroupColumn, a: &CgroupRow, b: &CgroupRow) -> Ordering {
+    match column {
+        CgroupColumn::Name => a.name.cmp(&b.name),
+        CgroupColumn::Cpu => compare_f64(Some(a.cpu), Some(b.cpu)),
+        CgroupColumn::Memory => compare_f64(a.memory, b.memory),
+        CgroupColumn::Limit => compare_f64(a.memory_max, b.memory_max),
+        CgroupColumn::Read => compare_f64(Some(a.read), Some(b.read)),
+        CgroupColumn::Write => compare_f64(Some(a.write), Some(b.write)),
+        CgroupColumn::Tasks => a.pids.cmp(&b.pids),
+    }
+}
+
+fn cell_text(column: CgroupColumn, row: &CgroupRow) -> String {
+    let optional = |value: Option<f64>, unlimited: &str| value.map(format_bytes).unwrap_or_else(|| unlimited.to_string());
+    match column {
+        CgroupColumn::Name => format!("{}{}", "  ".repeat(row.depth), row.name),
+        CgroupColumn::Cpu => format!("{:.1}", row.cpu),
+        CgroupColumn::Memory => optional(row.memory, "-"),
+        CgroupColumn::Limit => optional(row.memory_max, "none"),
+        CgroupColumn::Read => format_bytes(row.read),
+        CgroupColumn::Write => format_bytes(row.write),
+        CgroupColumn::Tasks => row.pids.map(|pids| pids.to_string()).unwrap_or_else(|| "-".to_string()),
+    }
+}
+
+// Append the children of `parent` in sorted order, each followed by its own expanded subtree
+fn add_rows(data: &State, children: &HashMap<Option<&str>, Vec<&Cgroup>>, parent: Option<&str>, depth: usize, rows: &mut Vec<CgroupRow>) {
+    let mut siblings: Vec<CgroupRow> = children.get(&parent).into_iter().flatten()
+        .map(|group| CgroupRow {
+            path: group.path.clone(),
+            name: group.name.clone(),
+            depth,
+            expanded: children.contains_key(&Some(group.path.as_str()))
+                .then(|| data.expanded_cgroups.contains(&group.path)),
+            selected: data.selected_cgroup.as_ref() == Some(&group.path),
+            cpu: group.cpu_history.latest_or_zero(),
+            memory: group.memory_history.latest(),
+            memory_max: group.memory_max,
+            read: group.io_read_history.latest_or_zero(),
+            write: group.io_write_history.latest_or_zero(),
+            pids: group.pids,
+        })
+        .collect();
+    data.cgroup_sort.sort(&mut siblings, compare);
+
+    for row in siblings {
+        let expanded = row.expanded == Some(true);
+        let path = row.path.clone();
+        rows.push(row);
+        if expanded {
+            add_rows(data, children, Some(&path), depth + 1, rows);
+        }
+    }
+}
+
+pub(crate) fn update_rows(data: &mut State) {
+    let mut children: HashMap<Option<&str>, Vec<&Cgroup>> = HashMap::new();
+    for group in data.cgroups.iter() {
+        children.entry(group.parent.as_deref()).or_default().push(group);
+    }
+    let mut rows = Vec::new();
+    add_rows(data, &children, None, 0, &mut rows);
+    data.cgroup_rows = rows.into_iter().collect();
+}
+
+fn cgroup_table() -> impl Widget<State> {
+    let mut headers = Flex::row().with_spacer(TOGGLE_WIDTH);
+    for (title, column, width) in COLUMNS {
+        headers.add_child(header(
+            title,
+            column,
+            width,
+            |data: &mut State| &mut data.cgroup_sort,
+            |data: &State| &data.cgroup_sort,
+            update_rows,
+        ));
+    }
+
+    let rows = List::new(|| {
+        let mut cells = Flex::row().with_child(
+            Label::new(|row: &CgroupRow, _env: &Env| match row.expanded {
+                Some(true) => "▾".to_string(),
+                Some(false) => "▸".to_string(),
+                None => String::new(),
+            })
+            .fix_width(TOGGLE_WIDTH)
+            .on_click(|ctx, row: &mut CgroupRow, _env| ctx.submit_command(TOGGLE_CGROUP.with(row.path.clone()))),
+        );
+        for (_, column, width) in COLUMNS {
+            cells.add_child(cell(width, move |row: &CgroupRow| cell_text(column, row)));
+        }
+        cells
+            .background(Painter::new(|ctx, row: &CgroupRow, _env| {
+                if row.selected {
+                    let rect = ctx.size().to_rect();
+                    ctx.fill(rect, &Color::rgb8(40, 70, 110));
+                }
+            }))
+            .on_click(|ctx, row: &mut CgroupRow, _env| ctx.submit_command(SELECT_CGROUP.with(row.path.clone())))
+    })
+    .lens(State::cgroup_rows);
+
+    Flex::column()
+        .with_child(headers)
+        .with_flex_child(Scroll::new(rows).vertical(), 1.0)
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+}
+
+fn graph(plot_type: PlotType, describe: impl Fn(&Cgroup) -> String + 'static) -> Flex<State> {
+    Flex::column()
+        .with_child(Label::new(move |data: &State, _env: &Env| {
+            data.current_cgroup().map(&describe).unwrap_or_default()
+        }))
+        .with_flex_child(UsageGraph::new(plot_type).expand_width(), 1.0)
+}
+
+// The cgroup v2 hierarchy as a tree, with history graphs for the selected group
+pub(crate) fn cgroup_panel() -> impl Widget<State> {
+    Flex::column()
+        .with_spacer(10.0)
+        .with_child(Label::new(|data: &State, _env: &Env| {
+            if data.cgroups.is_empty() {
+                return "No cgroup v2 hierarchy found".to_string();
+            }
+            match data.current_cgroup() {
+                Some(group) => format!("Selected: {}", group.path),
+                None => "Click a group to graph it".to_string(),
+            }
+        }))
+        .with_spacer(4.0)
+        // Makes the selected group the one the side panel's limits mode is relative to
+        .with_child(Button::new("Use for limits").on_click(|_ctx, data: &mut State, _env| {
+            if data.selected_cgroup.is_some() {
+                data.limits_cgroup = data.selected_cgroup.clone();
+                data.share_cgroup_focus();
+            }
+        }))
+        .with_spacer(4.0)
+        .with_flex_child(cgroup_table(), 1.0)
+        .with_spacer(8.0)
+        .with_flex_child(
+            Flex::row()
+                .with_flex_child(graph(PlotType::CgroupCpu, |group| {
+                    format!("CPU: {:.1}%", group.cpu_history.latest_or_zero())
+                }), 1.0)
+                .with_flex_child(graph(PlotType::CgroupMemory, |group| match group.memory_max {
+                    Some(max) => format!("Memory: {} / {}", format_bytes(group.memory_history.latest_or_zero()), format_bytes(max)),
+                    None => format!("Memory: {}", format_bytes(group.memory_history.latest_or_zero())),
+                }), 1.0)
+                .with_flex_child(graph(PlotType::CgroupIo, |group| format!(
+                    "I/O: read {}/s / write {}/s",
+                    format_bytes(group.io_read_history.latest_or_zero()),
+                    format_bytes(group.io_write_history.latest_or_zero())
+                )), 1.0),
+            1.0,
+        )
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+}
+
+pub(crate) fn default_sort() -> TableSort<CgroupColumn> {
+    TableSort::new(CgroupColumn::Cpu, true)
+}
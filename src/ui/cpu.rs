@@ -0,0 +1,67 @@
+use druid::{Env, Widget, WidgetExt};
+use druid::widget::{CrossAxisAlignment, Flex, Label, Scroll, ViewSwitcher};
+use im::Vector;
+use crate::history::TimeSeries;
+use crate::system::CPU_STATES;
+use crate::State;
+use crate::ui::usage_graph::{PlotType, UsageGraph};
+
+const CORES_PER_ROW: usize = 4;
+const CORE_GRAPH_HEIGHT: f64 = 140.0;
+
+// Latest percentage of each CPU state, e.g. "User 12% · System 3% · ..."
+fn describe(breakdown: &Vector<TimeSeries>) -> String {
+    CPU_STATES.iter()
+        .zip(breakdown.iter())
+        .map(|(state, history)| format!("{} {:.0}%", state, history.latest_or_zero()))
+        .collect::<Vec<_>>()
+        .join(" · ")
+}
+
+fn core_graph(core: usize) -> Flex<State> {
+    Flex::column()
+        .with_child(Label::new(move |data: &State, _env: &Env| {
+            let busy: f64 = data.system.core_time_breakdown.get(core)
+                .map(|breakdown| breakdown.iter().filter_map(|history| history.latest()).sum())
+                .unwrap_or(0.0);
+            format!("Core {}: {:.0}% busy", core + 1, busy)
+        }))
+        .with_child(UsageGraph::new(PlotType::CpuTime(Some(core))).expand_width().fix_height(CORE_GRAPH_HEIGHT))
+}
+
+fn core_graphs(cores: usize) -> Flex<State> {
+    let mut rows = Flex::column();
+    for first in (0..cores).step_by(CORES_PER_ROW) {
+        let mut row = Flex::row();
+        for core in first..first + CORES_PER_ROW {
+            // Pad the last row so its graphs line up with the ones above
+            if core < cores {
+                row.add_flex_child(core_graph(core), 1.0);
+            } else {
+                row.add_flex_spacer(1.0);
+            }
+        }
+        rows.add_child(row);
+        rows.add_spacer(8.0);
+    }
+    rows
+}
+
+// Where CPU time goes, so kernel time, I/O wait and hypervisor steal can be told apart from user load
+pub(crate) fn cpu_panel() -> impl Widget<State> {
+    Flex::column()
+        .with_spacer(10.0)
+        .with_child(Label::new(|data: &State, _env: &Env| {
+            format!("CPU Time (average): {}", describe(&data.system.cpu_time_breakdown))
+        }))
+        .with_flex_child(UsageGraph::new(PlotType::CpuTime(None)).expand_width(), 1.0)
+        .with_spacer(10.0)
+        .with_flex_child(
+            Scroll::new(ViewSwitcher::new(
+                |data: &State, _env: &Env| data.system.core_time_breakdown.len(),
+                |cores, _data, _env| Box::new(core_graphs(*cores)),
+            )).vertical(),
+            2.0,
+        )
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+}
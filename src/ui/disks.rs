@@ -0,0 +1,53 @@
+use druid::{Env, Widget, WidgetExt};
+use druid::widget::{CrossAxisAlignment, Flex, Label};
+use crate::diskio::BlockDevice;
+use crate::pressure::Resource;
+use crate::State;
+use crate::ui::pressure::pressure_graph;
+use crate::ui::usage_graph::{format_bytes, PlotType, UsageGraph};
+
+fn describe(disk: &BlockDevice) -> String {
+    format!(
+        "{}: read {}/s ({:.0} IOPS)  write {}/s ({:.0} IOPS)  await {:.1} ms  util {:.0}%",
+        disk.label(),
+        format_bytes(disk.read_bytes_history.latest_or_zero()),
+        disk.read_iops_history.latest_or_zero(),
+        format_bytes(disk.write_bytes_history.latest_or_zero()),
+        disk.write_iops_history.latest_or_zero(),
+        disk.await_history.latest_or_zero(),
+        disk.util_history.latest_or_zero(),
+    )
+}
+
+fn graph(title: &'static str, plot_type: PlotType) -> Flex<State> {
+    Flex::column()
+        .with_child(Label::new(title))
+        .with_flex_child(UsageGraph::new(plot_type).expand_width(), 1.0)
+}
+
+pub(crate) fn disk_panel() -> impl Widget<State> {
+    Flex::column()
+        .with_spacer(10.0)
+        .with_child(Label::new(|data: &State, _env: &Env| {
+            if data.disks.is_empty() {
+                return "No block devices found".to_string();
+            }
+            data.disks.iter().map(describe).collect::<Vec<_>>().join("\n")
+        }))
+        .with_spacer(4.0)
+        .with_flex_child(
+            Flex::row()
+                .with_flex_child(graph("Throughput", PlotType::DiskThroughput), 1.0)
+                .with_flex_child(graph("IOPS", PlotType::DiskIops), 1.0),
+            1.0,
+        )
+        .with_flex_child(
+            Flex::row()
+                .with_flex_child(graph("Average await", PlotType::DiskAwait), 1.0)
+                .with_flex_child(graph("Utilisation", PlotType::DiskUtil), 1.0),
+            1.0,
+        )
+        // System-wide I/O pressure, which shows whether the disks are actually holding tasks up
+        .with_flex_child(pressure_graph(Resource::Io), 1.0)
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+}
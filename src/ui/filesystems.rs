@@ -0,0 +1,144 @@
+use std::cmp::Ordering;
+use druid::{lens, Color, Data, Env, Lens, RenderContext, Widget, WidgetExt};
+use druid::widget::{CrossAxisAlignment, Flex, Label, List, Painter, Scroll, Stepper};
+use crate::filesystems::Filesystem;
+use crate::State;
+use crate::ui::table::{cell, compare_f64, header, TableSort};
+use crate::ui::usage_graph::format_bytes;
+
+// Used space or inodes, in percent, above which a filesystem is highlighted
+pub(crate) const DEFAULT_THRESHOLD: f64 = 90.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum FilesystemColumn {
+    Mount,
+    Device,
+    Type,
+    Used,
+    Available,
+    Total,
+    UsedPercent,
+    Inodes,
+}
+
+const COLUMNS: [(&str, FilesystemColumn, f64); 8] = [
+    ("Mount point", FilesystemColumn::Mount, 180.0),
+    ("Device", FilesystemColumn::Device, 160.0),
+    ("Type", FilesystemColumn::Type, 70.0),
+    ("Used", FilesystemColumn::Used, 90.0),
+    ("Available", FilesystemColumn::Available, 90.0),
+    ("Total", FilesystemColumn::Total, 90.0),
+    ("Use %", FilesystemColumn::UsedPercent, 70.0),
+    ("Inodes %", FilesystemColumn::Inodes, 80.0),
+];
+
+fn compare(column: FilesystemColumn, a: &FilesystemRow, b: &FilesystemRow) -> Ordering {
+    let (a, b) = (&a.filesystem, &b.filesystem);
+    match column {
+        FilesystemColumn::Mount => a.mount_point.cmp(&b.mount_point),
+        FilesystemColumn::Device => a.device.cmp(&b.device),
+        FilesystemColumn::Type => a.fs_type.cmp(&b.fs_type),
+        FilesystemColumn::Used => compare_f64(Some(a.used()), Some(b.used())),
+        FilesystemColumn::Available => compare_f64(Some(a.available), Some(b.available)),
+        FilesystemColumn::Total => compare_f64(Some(a.total), Some(b.total)),
+        FilesystemColumn::UsedPercent => compare_f64(Some(a.used_percent()), Some(b.used_percent())),
+        FilesystemColumn::Inodes => compare_f64(a.inodes_used_percent(), b.inodes_used_percent()),
+    }
+}
+
+#[derive(Clone, Data, Lens, Debug)]
+pub(crate) struct FilesystemRow {
+    filesystem: Filesystem,
+    // Space or inode usage is at or above the threshold
+    full: bool,
+}
+
+fn is_full(filesystem: &Filesystem, threshold: f64) -> bool {
+    filesystem.used_percent() >= threshold || filesystem.inodes_used_percent().is_some_and(|inodes| inodes >= threshold)
+}
+
+pub(crate) fn update_rows(data: &mut State) {
+    let mut rows: Vec<FilesystemRow> = data.filesystems.iter()
+        .map(|filesystem| FilesystemRow {
+            filesystem: filesystem.clone(),
+            full: is_full(filesystem, data.filesystem_threshold),
+        })
+        .collect();
+    data.filesystem_sort.sort(&mut rows, compare);
+    data.filesystem_rows = rows.into_iter().collect();
+}
+
+// The stepper writes back through its lens on every event, so only rebuild the rows on a real change
+fn threshold_lens() -> impl Lens<State, f64> {
+    lens::Map::new(
+        |data: &State| data.filesystem_threshold,
+        |data: &mut State, threshold| {
+            if threshold != data.filesystem_threshold {
+                data.filesystem_threshold = threshold;
+                update_rows(data);
+            }
+        },
+    )
+}
+
+pub(crate) fn filesystem_panel() -> impl Widget<State> {
+    let threshold = Flex::row()
+        .with_child(Label::new(|data: &State, _env: &Env| {
+            format!("Highlight above {:.0}% used", data.filesystem_threshold)
+        }))
+        .with_child(Stepper::new().with_range(50.0, 100.0).with_step(1.0).lens(threshold_lens()))
+        .with_spacer(16.0)
+        .with_child(Label::new(|data: &State, _env: &Env| {
+            let full: Vec<&str> = data.filesystems.iter()
+                .filter(|filesystem| is_full(filesystem, data.filesystem_threshold))
+                .map(|filesystem| filesystem.mount_point.as_str())
+                .collect();
+            if full.is_empty() { String::new() } else { format!("Nearly full: {}", full.join(", ")) }
+        }).with_text_color(Color::rgb8(255, 120, 100)));
+
+    let mut headers = Flex::row();
+    for (title, column, width) in COLUMNS {
+        headers.add_child(header(
+            title,
+            column,
+            width,
+            |data: &mut State| &mut data.filesystem_sort,
+            |data: &State| &data.filesystem_sort,
+            update_rows,
+        ));
+    }
+
+    let rows = List::new(|| {
+        Flex::row()
+            .with_child(cell(COLUMNS[0].2, |f: &Filesystem| f.mount_point.clone()))
+            .with_child(cell(COLUMNS[1].2, |f: &Filesystem| f.device.clone()))
+            .with_child(cell(COLUMNS[2].2, |f: &Filesystem| f.fs_type.clone()))
+            .with_child(cell(COLUMNS[3].2, |f: &Filesystem| format_bytes(f.used())))
+            .with_child(cell(COLUMNS[4].2, |f: &Filesystem| format_bytes(f.available)))
+            .with_child(cell(COLUMNS[5].2, |f: &Filesystem| format_bytes(f.total)))
+            .with_child(cell(COLUMNS[6].2, |f: &Filesystem| format!("{:.0}", f.used_percent())))
+            .with_child(cell(COLUMNS[7].2, |f: &Filesystem| {
+                f.inodes_used_percent().map(|inodes| format!("{:.0}", inodes)).unwrap_or_else(|| "-".to_string())
+            }))
+            .lens(FilesystemRow::filesystem)
+            .background(Painter::new(|ctx, row: &FilesystemRow, _env| {
+                if row.full {
+                    let rect = ctx.size().to_rect();
+                    ctx.fill(rect, &Color::rgb8(110, 30, 30));
+                }
+            }))
+    })
+    .lens(State::filesystem_rows);
+
+    Flex::column()
+        .with_spacer(10.0)
+        .with_child(threshold)
+        .with_spacer(8.0)
+        .with_child(headers)
+        .with_flex_child(Scroll::new(rows).vertical(), 1.0)
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+}
+
+pub(crate) fn default_sort() -> TableSort<FilesystemColumn> {
+    TableSort::new(FilesystemColumn::Mount, false)
+}
@@ -0,0 +1,83 @@
+use std::cmp::Ordering;
+use druid::{Widget, WidgetExt};
+use druid::widget::{Flex, List, Scroll};
+use crate::gpu::GpuProcessInfo;
+use crate::State;
+use crate::ui::table::{cell, compare_f64, header, TableSort};
+use crate::ui::usage_graph::format_bytes;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum GpuProcessColumn {
+    Gpu,
+    Pid,
+    Name,
+    User,
+    Memory,
+    Sm,
+}
+
+const COLUMNS: [(&str, GpuProcessColumn, f64); 6] = [
+    ("GPU", GpuProcessColumn::Gpu, 50.0),
+    ("PID", GpuProcessColumn::Pid, 70.0),
+    ("Name", GpuProcessColumn::Name, 180.0),
+    ("User", GpuProcessColumn::User, 100.0),
+    ("VRAM", GpuProcessColumn::Memory, 90.0),
+    ("SM %", GpuProcessColumn::Sm, 70.0),
+];
+
+fn compare(column: GpuProcessColumn, a: &GpuProcessInfo, b: &GpuProcessInfo) -> Ordering {
+    match column {
+        GpuProcessColumn::Gpu => a.gpu.cmp(&b.gpu),
+        GpuProcessColumn::Pid => a.pid.cmp(&b.pid),
+        GpuProcessColumn::Name => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
+        GpuProcessColumn::User => a.user.cmp(&b.user),
+        GpuProcessColumn::Memory => compare_f64(a.used_mem, b.used_mem),
+        GpuProcessColumn::Sm => compare_f64(a.sm_util, b.sm_util),
+    }
+}
+
+// Processes on the GPUs currently shown, in the order chosen by the column headers
+pub(crate) fn update_rows(data: &mut State) {
+    let mut rows: Vec<GpuProcessInfo> = data.visible_gpus().iter()
+        .flat_map(|gpu| gpu.processes.iter().cloned())
+        .collect();
+    data.gpu_process_sort.sort(&mut rows, compare);
+    data.gpu_process_rows = rows.into_iter().collect();
+}
+
+pub(crate) fn gpu_process_list() -> impl Widget<State> {
+    let mut headers = Flex::row();
+    for (title, column, width) in COLUMNS {
+        headers.add_child(header(
+            title,
+            column,
+            width,
+            |data: &mut State| &mut data.gpu_process_sort,
+            |data: &State| &data.gpu_process_sort,
+            update_rows,
+        ));
+    }
+
+    let rows = List::new(|| {
+        Flex::row()
+            .with_child(cell(COLUMNS[0].2, |p: &GpuProcessInfo| p.gpu.to_string()))
+            .with_child(cell(COLUMNS[1].2, |p: &GpuProcessInfo| p.pid.to_string()))
+            .with_child(cell(COLUMNS[2].2, |p: &GpuProcessInfo| p.name.clone()))
+            .with_child(cell(COLUMNS[3].2, |p: &GpuProcessInfo| p.user.clone()))
+            .with_child(cell(COLUMNS[4].2, |p: &GpuProcessInfo| {
+                p.used_mem.map(format_bytes).unwrap_or_else(|| "-".to_string())
+            }))
+            .with_child(cell(COLUMNS[5].2, |p: &GpuProcessInfo| {
+                p.sm_util.map(|util| format!("{:.0}", util)).unwrap_or_else(|| "-".to_string())
+            }))
+    })
+    .lens(State::gpu_process_rows);
+
+    Flex::column()
+        .with_child(headers)
+        .with_flex_child(Scroll::new(rows).vertical(), 1.0)
+}
+
+pub(crate) fn default_sort() -> TableSort<GpuProcessColumn> {
+    TableSort::new(GpuProcessColumn::Memory, true)
+}
@@ -1,51 +1,233 @@
-use druid::{Env, WidgetExt};
-use druid::widget::{Flex, Label};
+use druid::{Env, Widget, WidgetExt};
+use druid::widget::{Button, Either, Flex, Label, Tabs, ViewSwitcher};
+use crate::cgroups::Limits;
+use crate::gpu::GPU;
+use crate::history::TimeSeries;
+use crate::pressure::Resource;
+use crate::system::MEMORY_CATEGORIES;
 use crate::State;
-use crate::ui::usage_graph::{PlotType, UsageGraph};
+use crate::ui::cgroups::cgroup_panel;
+use crate::ui::cpu::cpu_panel;
+use crate::ui::disks::disk_panel;
+use crate::ui::filesystems::filesystem_panel;
+use crate::ui::gpu_processes::{gpu_process_list, update_rows as update_gpu_process_rows};
+use crate::ui::network::network_panel;
+use crate::ui::pressure::pressure_graph;
+use crate::ui::processes::process_list;
+use crate::ui::sensors::sensor_panel;
+use crate::ui::usage_graph::{format_bytes, PlotType, UsageGraph};
 
-pub (crate) fn main_panel() -> Flex<State> {
-    // Main content
+pub (crate) fn main_panel() -> impl Widget<State> {
+    Tabs::new()
+        .with_tab("Overview", overview())
+        .with_tab("CPU", cpu_panel())
+        .with_tab("Processes", process_list())
+        .with_tab("Cgroups", cgroup_panel())
+        .with_tab("Network", network_panel())
+        .with_tab("Disks", disk_panel())
+        .with_tab("Filesystems", filesystem_panel())
+        .with_tab("Sensors", sensor_panel())
+}
+
+fn overview() -> Flex<State> {
     Flex::column()
         .with_spacer(10.0)
         // Average CPU Usage plot
-        .with_child(Label::new(|_data: &State, _env: &Env| {
-            "CPU Usage (average)".to_string()
+        .with_child(Label::new(|data: &State, _env: &Env| match data.active_limits() {
+            Some((group, Limits { cpus: Some(cpus), .. })) => format!(
+                "CPU Usage ({}): {:.2} of {:.2} CPUs",
+                group.path, group.cpu_history.latest_or_zero() / 100.0, cpus
+            ),
+            _ => "CPU Usage (average)".to_string(),
         }))
         .with_flex_child(UsageGraph::new(PlotType::AverageCPU).expand_width(), 1.0)
 
-        // CPU Core Usage plot
-        .with_child(Label::new(|_data: &State, _env: &Env| {
-            "CPU Core Usage".to_string()
-        }))
-        .with_flex_child(UsageGraph::new(PlotType::PerCoreCPU).expand_width(), 1.0)
+        .with_flex_child(
+            Flex::row()
+                // CPU Core Usage plot
+                .with_flex_child(graph(PlotType::PerCoreCPU, |_data| "CPU Core Usage".to_string()), 1.0)
+                // CPU Frequency plot, to tell boosting from throttling
+                .with_flex_child(graph(PlotType::CpuFrequency, |data| {
+                    let cores = &data.system.cpu_freq_history;
+                    let average = cores.iter().map(TimeSeries::latest_or_zero).sum::<f64>() / cores.len().max(1) as f64;
+                    if data.system.cpu_freq_max > 0.0 {
+                        format!(
+                            "CPU Frequency: {:.0} MHz average ({:.0}-{:.0} MHz)",
+                            average, data.system.cpu_freq_min, data.system.cpu_freq_max
+                        )
+                    } else {
+                        format!("CPU Frequency: {:.0} MHz average", average)
+                    }
+                }), 1.0)
+                // CPU pressure plot: time runnable tasks waited for a CPU
+                .with_flex_child(pressure_graph(Resource::Cpu), 1.0),
+            1.0,
+        )
 
-        // RAM Usage plot
-        .with_child(Label::new(|data: &State, _env: &Env| {
-            format!(
-                "RAM Usage: {:.2} GB / {:.2}GB ",
-                data.system.used_mem / 1024.0, data.system.total_mem / 1024.0
-            )
-        }))
-        .with_flex_child(UsageGraph::new(PlotType::RAM).expand_width(), 1.0)
-        // GPU VRAM Usage plot
+        // RAM and swap plots side by side
+        .with_flex_child(
+            Flex::row()
+                // RAM Usage plot
+                .with_flex_child(graph(PlotType::RAM, |data| {
+                    if let Some((group, Limits { memory: Some(max), .. })) = data.active_limits() {
+                        return format!(
+                            "RAM Usage ({}): {} / {}",
+                            group.path, format_bytes(group.memory_history.latest_or_zero()), format_bytes(max)
+                        );
+                    }
+                    let breakdown: Vec<String> = MEMORY_CATEGORIES.iter()
+                        .zip(data.system.memory_breakdown.iter())
+                        .map(|(category, history)| format!("{} {}", category, format_bytes(history.latest_or_zero())))
+                        .collect();
+                    format!(
+                        "RAM Usage: {:.2} GB / {:.2}GB\n{}",
+                        data.system.used_mem / 1024.0, data.system.total_mem / 1024.0,
+                        breakdown.chunks(3).map(|line| line.join(" · ")).collect::<Vec<_>>().join("\n")
+                    )
+                }), 2.0)
+                // Swap Usage plot
+                .with_flex_child(graph(PlotType::Swap, |data| format!(
+                    "Swap Usage: {:.2} GB / {:.2}GB",
+                    data.system.used_swap / 1024.0, data.system.total_swap / 1024.0
+                )), 1.0)
+                // Swap activity plot, which shows when the machine is thrashing
+                .with_flex_child(graph(PlotType::SwapActivity, |data| format!(
+                    "Swap: in {}/s / out {}/s",
+                    format_bytes(data.system.swap_in_history.latest_or_zero()),
+                    format_bytes(data.system.swap_out_history.latest_or_zero())
+                )), 1.0)
+                // Memory pressure plot: time lost to reclaim and refaults
+                .with_flex_child(pressure_graph(Resource::Memory), 1.0),
+            1.0,
+        )
+        // GPU plots collapse to a single line when no GPU is available
+        .with_flex_child(
+            Either::new(
+                |data: &State, _env: &Env| !data.gpus.is_empty(),
+                gpu_panel(),
+                Label::new("GPU: no GPU available"),
+            ),
+            4.0,
+        )
+}
+
+fn gpu_panel() -> Flex<State> {
+    Flex::column()
+        // Device picker, only shown when there is more than one GPU to choose from
+        .with_child(ViewSwitcher::new(
+            |data: &State, _env: &Env| data.gpus.len(),
+            |count, _data, _env| Box::new(gpu_selector(*count)),
+        ))
         .with_child(Label::new(|data: &State, _env: &Env| {
-            format!(
-                "GPU: {} \nVRAM Usage: {:.2} GB / {:.2}GB ",
-                data.gpu.name,
-                data.gpu.used_mem / 1024.0 / 1024.0 / 1024.0,
-                data.gpu.total_mem / 1024.0 / 1024.0 / 1024.0
-            )
+            data.visible_gpus().iter()
+                .map(|gpu| format!("GPU {}: {}", gpu.index, gpu.name))
+                .collect::<Vec<_>>()
+                .join("\n")
         }))
-        .with_flex_child(UsageGraph::new(PlotType::GPU).expand_width(), 1.0)
-        // GPU Fan Speed plot
-        .with_flex_child(UsageGraph::new(PlotType::GPUFan).expand_width(), 1.0)
-        // GPU Temperature plot
-        .with_child(Label::new(|data: &State, _env: &Env| {
-            let temp = data.gpu.temp_history[data.gpu.temp_history.len() - 1];
-            format!(
-                "GPU Temperature (°C): {:.2}",
-                temp,
-            )
+        // Graphs are laid out two per row to keep them readable alongside the CPU and RAM plots
+        .with_flex_child(
+            Flex::row()
+                // GPU VRAM Usage plot
+                .with_flex_child(gpu_graph(PlotType::GPU, |gpu| format!(
+                    "VRAM Usage: {:.2} GB / {:.2}GB",
+                    gpu.used_mem / 1024.0 / 1024.0 / 1024.0,
+                    gpu.total_mem / 1024.0 / 1024.0 / 1024.0
+                )), 1.0)
+                // GPU Utilisation plot
+                .with_flex_child(gpu_graph(PlotType::GPUUtil, |gpu| format!(
+                    "Utilisation: SM {:.0}% / Memory {:.0}%",
+                    gpu.util_history.latest_or_zero(),
+                    gpu.mem_util_history.latest_or_zero()
+                )), 1.0),
+            1.0,
+        )
+        .with_flex_child(
+            Flex::row()
+                // GPU Fan Speed plot
+                .with_flex_child(gpu_graph(PlotType::GPUFan, |gpu| {
+                    let fans: Vec<String> = gpu.fan_speed_history.iter()
+                        .map(|fan| format!("{:.0}", fan.latest_or_zero()))
+                        .collect();
+                    format!("Fan Speed (RPM): {}", fans.join(" / "))
+                }), 1.0)
+                // GPU Temperature plot
+                .with_flex_child(gpu_graph(PlotType::GPUTemp, |gpu| format!(
+                    "GPU Temperature (°C): {:.2}",
+                    gpu.temp_history.latest_or_zero()
+                )), 1.0),
+            1.0,
+        )
+        .with_flex_child(
+            Flex::row()
+                // GPU Power plot
+                .with_flex_child(gpu_graph(PlotType::GPUPower, |gpu| format!(
+                    "Power: {:.0} W / {:.0} W limit",
+                    gpu.power_history.latest_or_zero(),
+                    gpu.power_limit
+                )), 1.0)
+                // GPU Clocks plot
+                .with_flex_child(gpu_graph(PlotType::GPUClock, |gpu| format!(
+                    "Clocks: Graphics {:.0} MHz / Memory {:.0} MHz",
+                    gpu.clock_history.latest_or_zero(),
+                    gpu.mem_clock_history.latest_or_zero()
+                )), 1.0),
+            1.0,
+        )
+        // GPU PCIe throughput plot
+        .with_flex_child(gpu_graph(PlotType::GPUPcie, |gpu| format!(
+            "PCIe: RX {}/s / TX {}/s",
+            format_bytes(gpu.pcie_rx_history.latest_or_zero()),
+            format_bytes(gpu.pcie_tx_history.latest_or_zero())
+        )), 1.0)
+        // Per-process GPU usage, below the graphs
+        .with_child(Label::new("GPU Processes"))
+        .with_flex_child(gpu_process_list(), 1.0)
+}
+
+// A graph headed by a label built from the current readings
+fn graph(plot_type: PlotType, describe: impl Fn(&State) -> String + 'static) -> Flex<State> {
+    Flex::column()
+        .with_child(Label::new(move |data: &State, _env: &Env| describe(data)))
+        .with_flex_child(UsageGraph::new(plot_type).expand_width(), 1.0)
+}
+
+// A GPU graph headed by a label built from each visible GPU's current readings
+fn gpu_graph(plot_type: PlotType, describe: impl Fn(&GPU) -> String + 'static) -> Flex<State> {
+    Flex::column()
+        .with_child(Label::new(move |data: &State, _env: &Env| {
+            let gpus = data.visible_gpus();
+            if gpus.len() == 1 {
+                return describe(gpus[0]);
+            }
+            gpus.iter()
+                .map(|gpu| format!("GPU {} {}", gpu.index, describe(gpu)))
+                .collect::<Vec<_>>()
+                .join("\n")
         }))
-        .with_flex_child(UsageGraph::new(PlotType::GPUTemp).expand_width(), 1.0)
-}
\ No newline at end of file
+        .with_flex_child(UsageGraph::new(plot_type).expand_width(), 1.0)
+}
+
+fn gpu_selector(count: usize) -> Flex<State> {
+    let mut row = Flex::row();
+    if count < 2 {
+        return row;
+    }
+
+    row.add_child(Button::dynamic(|data: &State, _env: &Env| {
+        if data.gpu_view.is_none() { "[All GPUs]".to_string() } else { "All GPUs".to_string() }
+    }).on_click(|_ctx, data: &mut State, _env| {
+        data.gpu_view = None;
+        update_gpu_process_rows(data);
+    }));
+
+    for index in 0..count {
+        row.add_spacer(4.0);
+        row.add_child(Button::dynamic(move |data: &State, _env: &Env| {
+            if data.gpu_view == Some(index) { format!("[GPU {}]", index) } else { format!("GPU {}", index) }
+        }).on_click(move |_ctx, data: &mut State, _env| {
+            data.gpu_view = Some(index);
+            update_gpu_process_rows(data);
+        }));
+    }
+    row
+}
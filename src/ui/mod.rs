@@ -1,5 +1,15 @@
 mod side_panel;
 mod main_panel;
+pub(crate) mod cgroups;
+pub(crate) mod cpu;
+pub(crate) mod disks;
+pub(crate) mod filesystems;
+pub(crate) mod gpu_processes;
+pub(crate) mod network;
+pub(crate) mod pressure;
+pub(crate) mod processes;
+pub(crate) mod sensors;
+pub(crate) mod table;
 pub(crate) mod usage_graph;
 
 use druid::{Widget, WidgetExt};
@@ -0,0 +1,40 @@
+use druid::{Env, Widget, WidgetExt};
+use druid::widget::{Checkbox, CrossAxisAlignment, Flex, Label};
+use crate::State;
+use crate::ui::usage_graph::{format_bytes, format_count, PlotType, UsageGraph};
+
+pub(crate) fn network_panel() -> impl Widget<State> {
+    let filters = Flex::row()
+        .with_child(Checkbox::new("Show loopback").lens(State::show_loopback))
+        .with_spacer(16.0)
+        .with_child(Checkbox::new("Show virtual interfaces").lens(State::show_virtual_interfaces));
+
+    Flex::column()
+        .with_spacer(10.0)
+        .with_child(filters)
+        .with_spacer(4.0)
+        // Current rates of each interface shown in the graphs
+        .with_child(Label::new(|data: &State, _env: &Env| {
+            let interfaces = data.visible_interfaces();
+            if interfaces.is_empty() {
+                return "No network interfaces to show".to_string();
+            }
+            interfaces.iter()
+                .map(|interface| format!(
+                    "{}: RX {}/s ({} packets/s)  TX {}/s ({} packets/s)",
+                    interface.name,
+                    format_bytes(interface.rx_bytes_history.latest_or_zero()),
+                    format_count(interface.rx_packets_history.latest_or_zero()),
+                    format_bytes(interface.tx_bytes_history.latest_or_zero()),
+                    format_count(interface.tx_packets_history.latest_or_zero()),
+                ))
+                .collect::<Vec<_>>()
+                .join("\n")
+        }))
+        .with_spacer(4.0)
+        .with_child(Label::new("Throughput"))
+        .with_flex_child(UsageGraph::new(PlotType::NetworkBytes).expand_width(), 1.0)
+        .with_child(Label::new("Packets"))
+        .with_flex_child(UsageGraph::new(PlotType::NetworkPackets).expand_width(), 1.0)
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+}
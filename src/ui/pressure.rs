@@ -0,0 +1,34 @@
+use druid::{Env, Widget, WidgetExt};
+use druid::widget::{Either, Flex, Label};
+use crate::pressure::{PressureLine, Resource};
+use crate::State;
+use crate::ui::usage_graph::{PlotType, UsageGraph};
+
+fn averages(kind: &str, line: &PressureLine) -> String {
+    format!("{} {:.2} / {:.2} / {:.2}", kind, line.avg10, line.avg60, line.avg300)
+}
+
+fn describe(data: &State, resource: Resource) -> String {
+    let Some(pressure) = data.resource_pressure(resource) else { return String::new() };
+    let mut text = format!(
+        "{} Pressure: {:.1}% stalled",
+        resource.title(), pressure.some_history.latest_or_zero()
+    );
+    // The kernel's own 10s / 60s / 300s averages, for comparison with longer windows
+    text.push_str(&format!("\n{}", averages("some", &pressure.latest.some)));
+    if let Some(full) = &pressure.latest.full {
+        text.push_str(&format!("  {}", averages("full", full)));
+    }
+    text
+}
+
+// Time tasks spent stalled on a resource, or a note when the kernel has no PSI
+pub(crate) fn pressure_graph(resource: Resource) -> impl Widget<State> {
+    Either::new(
+        move |data: &State, _env: &Env| data.resource_pressure(resource).is_some(),
+        Flex::column()
+            .with_child(Label::new(move |data: &State, _env: &Env| describe(data, resource)))
+            .with_flex_child(UsageGraph::new(PlotType::Pressure(resource)).expand_width(), 1.0),
+        Label::new(format!("{} Pressure: not available (needs Linux 4.20+ with PSI enabled)", resource.title())),
+    )
+}
@@ -0,0 +1,414 @@
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use druid::{Color, Data, Env, Lens, RenderContext, Widget, WidgetExt};
+use druid::widget::{Button, CrossAxisAlignment, Flex, Label, List, Painter, Scroll, Stepper, TextBox, ViewSwitcher};
+use im::Vector;
+use sysinfo::Signal;
+use crate::processes::{format_age, parse_cpu_list, ProcessAction, ProcessInfo};
+use crate::{State, SELECT_PROCESS, TOGGLE_PROCESS};
+use crate::ui::table::{cell, compare_f64, header, TableSort};
+use crate::ui::usage_graph::format_bytes;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum ProcessColumn {
+    Pid,
+    Name,
+    User,
+    Cpu,
+    Rss,
+    TreeCpu,
+    TreeRss,
+    Virtual,
+    State,
+    Started,
+    Command,
+}
+
+const COLUMNS: [(&str, ProcessColumn, f64); 11] = [
+    ("PID", ProcessColumn::Pid, 70.0),
+    ("Name", ProcessColumn::Name, 200.0),
+    ("User", ProcessColumn::User, 90.0),
+    ("CPU %", ProcessColumn::Cpu, 70.0),
+    ("RSS", ProcessColumn::Rss, 90.0),
+    // Summed over the process and all its descendants, only shown in the tree
+    ("Σ CPU %", ProcessColumn::TreeCpu, 80.0),
+    ("Σ RSS", ProcessColumn::TreeRss, 90.0),
+    ("Virtual", ProcessColumn::Virtual, 90.0),
+    ("State", ProcessColumn::State, 90.0),
+    ("Age", ProcessColumn::Started, 90.0),
+    ("Command", ProcessColumn::Command, 500.0),
+];
+
+const TOGGLE_WIDTH: f64 = 20.0;
+
+fn columns(tree: bool) -> impl Iterator<Item = (&'static str, ProcessColumn, f64)> {
+    COLUMNS.into_iter()
+        .filter(move |(_, column, _)| tree || !matches!(column, ProcessColumn::TreeCpu | ProcessColumn::TreeRss))
+}
+
+#[derive(Clone, Data, Lens, Debug)]
+pub(crate) struct ProcessRow {
+    info: ProcessInfo,
+    selected: bool,
+    // Nesting level in the tree, 0 in the flat list
+    depth: usize,
+    // Whether the children are shown, or None for a process without children
+    expanded: Option<bool>,
+    tree_cpu: f64,
+    tree_rss: f64,
+}
+
+impl ProcessRow {
+    fn new(info: &ProcessInfo) -> Self {
+        ProcessRow {
+            info: info.clone(),
+            selected: false,
+            depth: 0,
+            expanded: None,
+            tree_cpu: info.cpu,
+            tree_rss: info.rss,
+        }
+    }
+}
+
+fn compare(column: ProcessColumn, a: &ProcessRow, b: &ProcessRow) -> Ordering {
+    let (a_info, b_info) = (&a.info, &b.info);
+    match column {
+        ProcessColumn::Pid => a_info.pid.cmp(&b_info.pid),
+        ProcessColumn::Name => a_info.name.to_lowercase().cmp(&b_info.name.to_lowercase()),
+        ProcessColumn::User => a_info.user.cmp(&b_info.user),
+        ProcessColumn::Cpu => compare_f64(Some(a_info.cpu), Some(b_info.cpu)),
+        ProcessColumn::Rss => compare_f64(Some(a_info.rss), Some(b_info.rss)),
+        ProcessColumn::TreeCpu => compare_f64(Some(a.tree_cpu), Some(b.tree_cpu)),
+        ProcessColumn::TreeRss => compare_f64(Some(a.tree_rss), Some(b.tree_rss)),
+        ProcessColumn::Virtual => compare_f64(Some(a_info.virtual_mem), Some(b_info.virtual_mem)),
+        ProcessColumn::State => a_info.state.cmp(&b_info.state),
+        // Youngest first when ascending, matching the age shown in the column
+        ProcessColumn::Started => b_info.start_time.cmp(&a_info.start_time),
+        ProcessColumn::Command => a_info.cmdline.cmp(&b_info.cmdline),
+    }
+}
+
+fn cell_text(column: ProcessColumn, row: &ProcessRow) -> String {
+    let info = &row.info;
+    match column {
+        ProcessColumn::Pid => info.pid.to_string(),
+        ProcessColumn::Name => format!("{}{}", "  ".repeat(row.depth), info.name),
+        ProcessColumn::User => info.user.clone(),
+        ProcessColumn::Cpu => format!("{:.1}", info.cpu),
+        ProcessColumn::Rss => format_bytes(info.rss),
+        ProcessColumn::TreeCpu => format!("{:.1}", row.tree_cpu),
+        ProcessColumn::TreeRss => format_bytes(row.tree_rss),
+        ProcessColumn::Virtual => format_bytes(info.virtual_mem),
+        ProcessColumn::State => info.state.clone(),
+        ProcessColumn::Started => format_age(info.start_time),
+        ProcessColumn::Command => info.cmdline.clone(),
+    }
+}
+
+// Parent/child links between the listed processes, with each subtree's summed usage
+struct ProcessTree<'a> {
+    children: HashMap<Option<u32>, Vec<&'a ProcessInfo>>,
+    totals: HashMap<u32, (f64, f64)>,
+}
+
+impl<'a> ProcessTree<'a> {
+    fn new(processes: &'a Vector<ProcessInfo>) -> Self {
+        let pids: HashSet<u32> = processes.iter().map(|process| process.pid).collect();
+        let mut children: HashMap<Option<u32>, Vec<&ProcessInfo>> = HashMap::new();
+        for process in processes {
+            // Processes whose parent isn't listed (e.g. hidden by permissions) become roots
+            let parent = process.parent.filter(|parent| pids.contains(parent) && *parent != process.pid);
+            children.entry(parent).or_default().push(process);
+        }
+
+        let mut tree = ProcessTree { children, totals: HashMap::new() };
+        let roots = tree.children.get(&None).cloned().unwrap_or_default();
+        for root in roots {
+            tree.sum_subtree(root);
+        }
+
+        // Whatever wasn't reached hangs off a parent cycle, which pid reuse can produce in a
+        // snapshot. Break each cycle at its lowest pid, which then becomes a root.
+        let mut unreached: Vec<&ProcessInfo> = processes.iter()
+            .filter(|process| !tree.totals.contains_key(&process.pid))
+            .collect();
+        unreached.sort_by_key(|process| process.pid);
+        for process in unreached {
+            if tree.totals.contains_key(&process.pid) {
+                continue;
+            }
+            if let Some(parent) = process.parent
+                && let Some(siblings) = tree.children.get_mut(&Some(parent))
+            {
+                siblings.retain(|sibling| sibling.pid != process.pid);
+                if siblings.is_empty() {
+                    tree.children.remove(&Some(parent));
+                }
+            }
+            tree.children.entry(None).or_default().push(process);
+            tree.sum_subtree(process);
+        }
+        tree
+    }
+
+    fn sum_subtree(&mut self, process: &'a ProcessInfo) -> (f64, f64) {
+        let mut total = (process.cpu, process.rss);
+        let children = self.children.get(&Some(process.pid)).cloned().unwrap_or_default();
+        for child in children {
+            let (cpu, rss) = self.sum_subtree(child);
+            total.0 += cpu;
+            total.1 += rss;
+        }
+        self.totals.insert(process.pid, total);
+        total
+    }
+
+    // Append the children of `parent` in sorted order, each followed by its own expanded subtree
+    fn add_rows(
+        &self,
+        collapsed: &HashSet<u32>,
+        sort: &TableSort<ProcessColumn>,
+        parent: Option<u32>,
+        depth: usize,
+        rows: &mut Vec<ProcessRow>,
+    ) {
+        let mut siblings: Vec<ProcessRow> = self.children.get(&parent).into_iter().flatten()
+            .map(|info| {
+                let (tree_cpu, tree_rss) = self.totals.get(&info.pid).copied().unwrap_or((info.cpu, info.rss));
+                let expanded = self.children.contains_key(&Some(info.pid))
+                    .then(|| !collapsed.contains(&info.pid));
+                ProcessRow { depth, expanded, tree_cpu, tree_rss, ..ProcessRow::new(info) }
+            })
+            .collect();
+        sort.sort(&mut siblings, compare);
+
+        for row in siblings {
+            let pid = row.info.pid;
+            let expanded = row.expanded == Some(true);
+            rows.push(row);
+            if expanded {
+                self.add_rows(collapsed, sort, Some(pid), depth + 1, rows);
+            }
+        }
+    }
+}
+
+pub(crate) fn update_rows(data: &mut State) {
+    let mut rows = Vec::new();
+    if data.process_tree {
+        ProcessTree::new(&data.processes).add_rows(&data.collapsed_processes, &data.process_sort, None, 0, &mut rows);
+    } else {
+        rows = data.processes.iter().map(ProcessRow::new).collect();
+        data.process_sort.sort(&mut rows, compare);
+    }
+    for row in &mut rows {
+        row.selected = data.selected_pid == Some(row.info.pid);
+    }
+    data.process_rows = rows.into_iter().collect();
+}
+
+fn selected_process(data: &State) -> Option<&ProcessInfo> {
+    let pid = data.selected_pid?;
+    data.processes.iter().find(|process| process.pid == pid)
+}
+
+fn run_action(data: &mut State, action: ProcessAction) {
+    let Some(process) = selected_process(data) else {
+        data.process_status = "Select a process first".to_string();
+        return;
+    };
+    data.process_status = match data.process_control.apply(process.pid, process.start_time, &action) {
+        Ok(message) | Err(message) => message,
+    };
+}
+
+fn action_button(title: &str, action: impl Fn(&State) -> Result<ProcessAction, String> + 'static) -> impl Widget<State> {
+    Button::new(title).on_click(move |_ctx, data: &mut State, _env| match action(data) {
+        Ok(action) => run_action(data, action),
+        Err(err) => data.process_status = err,
+    })
+}
+
+// Signals, nice value and CPU affinity for the selected process
+fn process_actions() -> impl Widget<State> {
+    let signal = |signal| move |_data: &State| Ok(ProcessAction::Signal(signal));
+
+    Flex::column()
+        .with_child(Label::new(|data: &State, _env: &Env| match selected_process(data) {
+            Some(process) => format!("Selected: {} ({})", process.pid, process.name),
+            None => "Click a process to select it".to_string(),
+        }))
+        .with_spacer(4.0)
+        .with_child(
+            Flex::row()
+                .with_child(action_button("Terminate", signal(Signal::Term)))
+                .with_spacer(4.0)
+                .with_child(action_button("Kill", signal(Signal::Kill)))
+                .with_spacer(4.0)
+                .with_child(action_button("Suspend", signal(Signal::Stop)))
+                .with_spacer(4.0)
+                .with_child(action_button("Resume", signal(Signal::Continue)))
+                .with_spacer(16.0)
+                .with_child(Label::new(|data: &State, _env: &Env| format!("Nice: {:.0}", data.nice)))
+                .with_child(Stepper::new().with_range(-20.0, 19.0).with_step(1.0).lens(State::nice))
+                .with_child(action_button("Renice", |data| Ok(ProcessAction::Renice(data.nice as i32))))
+                .with_spacer(16.0)
+                .with_child(TextBox::new().with_placeholder("CPUs, e.g. 0-3,6").fix_width(140.0).lens(State::affinity))
+                .with_child(action_button("Set affinity", |data| {
+                    parse_cpu_list(&data.affinity).map(ProcessAction::Affinity)
+                })),
+        )
+        .with_spacer(4.0)
+        .with_child(Label::new(|data: &State, _env: &Env| data.process_status.clone()))
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+}
+
+fn process_table(tree: bool) -> impl Widget<State> {
+    let mut headers = Flex::row();
+    if tree {
+        headers.add_spacer(TOGGLE_WIDTH);
+    }
+    for (title, column, width) in columns(tree) {
+        headers.add_child(header(
+            title,
+            column,
+            width,
+            |data: &mut State| &mut data.process_sort,
+            |data: &State| &data.process_sort,
+            update_rows,
+        ));
+    }
+
+    let rows = List::new(move || {
+        let mut cells = Flex::row();
+        if tree {
+            cells.add_child(
+                Label::new(|row: &ProcessRow, _env: &Env| match row.expanded {
+                    Some(true) => "▾".to_string(),
+                    Some(false) => "▸".to_string(),
+                    None => String::new(),
+                })
+                .fix_width(TOGGLE_WIDTH)
+                .on_click(|ctx, row: &mut ProcessRow, _env| ctx.submit_command(TOGGLE_PROCESS.with(row.info.pid))),
+            );
+        }
+        for (_, column, width) in columns(tree) {
+            cells.add_child(cell(width, move |row: &ProcessRow| cell_text(column, row)));
+        }
+        cells
+            .background(Painter::new(|ctx, row: &ProcessRow, _env| {
+                if row.selected {
+                    let rect = ctx.size().to_rect();
+                    ctx.fill(rect, &Color::rgb8(40, 70, 110));
+                }
+            }))
+            .on_click(|ctx, row: &mut ProcessRow, _env| ctx.submit_command(SELECT_PROCESS.with(row.info.pid)))
+    })
+    .lens(State::process_rows);
+
+    // The command line column is wide, so the whole table scrolls sideways under its headers
+    Scroll::new(
+        Flex::column()
+            .with_child(headers)
+            .with_flex_child(Scroll::new(rows).vertical(), 1.0),
+    )
+    .horizontal()
+}
+
+pub(crate) fn process_list() -> impl Widget<State> {
+    let view_toggle = Button::dynamic(|data: &State, _env: &Env| {
+        if data.process_tree { "Show as list".to_string() } else { "Show as tree".to_string() }
+    })
+    .on_click(|_ctx, data: &mut State, _env| {
+        data.process_tree = !data.process_tree;
+        update_rows(data);
+    });
+
+    Flex::column()
+        .with_child(process_actions())
+        .with_spacer(8.0)
+        .with_child(view_toggle)
+        .with_spacer(4.0)
+        .with_flex_child(
+            ViewSwitcher::new(
+                |data: &State, _env: &Env| data.process_tree,
+                |tree, _data, _env| Box::new(process_table(*tree)),
+            ),
+            1.0,
+        )
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+}
+
+pub(crate) fn default_sort() -> TableSort<ProcessColumn> {
+    TableSort::new(ProcessColumn::Cpu, true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(pid: u32, parent: Option<u32>, cpu: f64, rss: f64) -> ProcessInfo {
+        ProcessInfo {
+            pid,
+            parent,
+            name: format!("proc{}", pid),
+            user: "root".to_string(),
+            cpu,
+            rss,
+            virtual_mem: 0.0,
+            state: "Sleeping".to_string(),
+            start_time: 0,
+            cmdline: String::new(),
+        }
+    }
+
+    // (pid, depth, expanded, tree CPU, tree RSS) of each row
+    fn rows(processes: &Vector<ProcessInfo>, collapsed: &[u32]) -> Vec<(u32, usize, Option<bool>, f64, f64)> {
+        let mut rows = Vec::new();
+        let collapsed = collapsed.iter().copied().collect();
+        ProcessTree::new(processes).add_rows(&collapsed, &TableSort::new(ProcessColumn::Pid, false), None, 0, &mut rows);
+        rows.iter().map(|row| (row.info.pid, row.depth, row.expanded, row.tree_cpu, row.tree_rss)).collect()
+    }
+
+    #[test]
+    fn sums_subtrees_and_adopts_orphans_and_cycles() {
+        let processes: Vector<ProcessInfo> = [
+            process(1, None, 1.0, 100.0),
+            process(10, Some(1), 2.0, 200.0),
+            process(11, Some(10), 4.0, 400.0),
+            // Parent not listed, e.g. hidden by permissions or already gone
+            process(20, Some(999), 8.0, 800.0),
+            // Parents of each other after pid reuse between two reads
+            process(30, Some(31), 16.0, 1600.0),
+            process(31, Some(30), 32.0, 3200.0),
+        ].into_iter().collect();
+
+        assert_eq!(rows(&processes, &[]), [
+            (1, 0, Some(true), 7.0, 700.0),
+            (10, 1, Some(true), 6.0, 600.0),
+            (11, 2, None, 4.0, 400.0),
+            (20, 0, None, 8.0, 800.0),
+            (30, 0, Some(true), 48.0, 4800.0),
+            (31, 1, None, 32.0, 3200.0),
+        ]);
+    }
+
+    #[test]
+    fn collapsed_processes_hide_their_subtree() {
+        let processes: Vector<ProcessInfo> = [
+            process(1, None, 1.0, 100.0),
+            process(10, Some(1), 2.0, 200.0),
+            process(11, Some(10), 4.0, 400.0),
+            process(12, Some(1), 8.0, 800.0),
+        ].into_iter().collect();
+
+        // The collapsed row still shows the totals of everything below it
+        assert_eq!(rows(&processes, &[10]), [
+            (1, 0, Some(true), 15.0, 1500.0),
+            (10, 1, Some(false), 6.0, 600.0),
+            (12, 1, None, 8.0, 800.0),
+        ]);
+        assert_eq!(rows(&processes, &[1]), [(1, 0, Some(false), 15.0, 1500.0)]);
+    }
+}
@@ -0,0 +1,117 @@
+use druid::{Color, Data, Env, Lens, RenderContext, Widget, WidgetExt};
+use druid::widget::{CrossAxisAlignment, Flex, Label, List, Painter, Scroll, ViewSwitcher};
+use crate::sensors::SensorKind;
+use crate::{State, CHART_SENSOR};
+use crate::ui::table::cell;
+use crate::ui::usage_graph::{PlotType, UsageGraph};
+
+#[derive(Clone, Data, Lens, Debug)]
+pub(crate) struct SensorRow {
+    id: String,
+    chip: String,
+    label: String,
+    unit: &'static str,
+    value: Option<f64>,
+    critical: Option<f64>,
+    charted: bool,
+}
+
+impl SensorRow {
+    fn over_critical(&self) -> bool {
+        matches!((self.value, self.critical), (Some(value), Some(critical)) if value >= critical)
+    }
+}
+
+pub(crate) fn update_rows(data: &mut State) {
+    data.sensor_rows = data.sensors.iter()
+        .map(|sensor| SensorRow {
+            id: sensor.id.clone(),
+            chip: sensor.chip.clone(),
+            label: sensor.label.clone(),
+            unit: sensor.kind.unit(),
+            value: sensor.history.latest(),
+            critical: sensor.critical,
+            charted: data.charted_sensors.contains(&sensor.id),
+        })
+        .collect();
+}
+
+fn format_reading(value: Option<f64>, unit: &str) -> String {
+    match value {
+        Some(value) => format!("{:.1} {}", value, unit),
+        None => "-".to_string(),
+    }
+}
+
+fn sensor_list() -> impl Widget<State> {
+    let headers = Flex::row()
+        .with_spacer(30.0)
+        .with_child(Label::new("Chip").fix_width(120.0))
+        .with_child(Label::new("Sensor").fix_width(180.0))
+        .with_child(Label::new("Value").fix_width(90.0))
+        .with_child(Label::new("Critical").fix_width(90.0));
+
+    let rows = List::new(|| {
+        Flex::row()
+            // Ticking a sensor adds it to the graph of its kind
+            .with_child(
+                Label::new(|row: &SensorRow, _env: &Env| if row.charted { "☑".to_string() } else { "☐".to_string() })
+                    .fix_width(30.0)
+                    .on_click(|ctx, row: &mut SensorRow, _env| ctx.submit_command(CHART_SENSOR.with(row.id.clone()))),
+            )
+            .with_child(cell(120.0, |row: &SensorRow| row.chip.clone()))
+            .with_child(cell(180.0, |row: &SensorRow| row.label.clone()))
+            .with_child(cell(90.0, |row: &SensorRow| format_reading(row.value, row.unit)))
+            .with_child(cell(90.0, |row: &SensorRow| format_reading(row.critical, row.unit)))
+            .background(Painter::new(|ctx, row: &SensorRow, _env| {
+                if row.over_critical() {
+                    let rect = ctx.size().to_rect();
+                    ctx.fill(rect, &Color::rgb8(110, 30, 30));
+                }
+            }))
+    })
+    .lens(State::sensor_rows);
+
+    Flex::column()
+        .with_child(headers)
+        .with_flex_child(Scroll::new(rows).vertical(), 1.0)
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+}
+
+// Bit per SensorKind with at least one charted sensor, so the graphs are rebuilt only when that changes
+fn charted_kinds(data: &State) -> u8 {
+    SensorKind::ALL.iter().enumerate()
+        .filter(|(_, kind)| !data.sensors_to_chart(**kind).is_empty())
+        .fold(0, |mask, (bit, _)| mask | 1 << bit)
+}
+
+fn sensor_graphs(mask: u8) -> Flex<State> {
+    let mut graphs = Flex::column();
+    for (bit, kind) in SensorKind::ALL.into_iter().enumerate() {
+        if mask & 1 << bit != 0 {
+            graphs.add_child(Label::new(kind.title()));
+            graphs.add_flex_child(UsageGraph::new(PlotType::Sensors(kind)).expand_width(), 1.0);
+        }
+    }
+    if mask == 0 {
+        graphs.add_child(Label::new("Tick sensors in the list to chart them"));
+    }
+    graphs
+}
+
+pub(crate) fn sensor_panel() -> impl Widget<State> {
+    Flex::column()
+        .with_spacer(10.0)
+        .with_child(Label::new(|data: &State, _env: &Env| {
+            if data.sensors.is_empty() { "No sensors found".to_string() } else { String::new() }
+        }))
+        .with_flex_child(sensor_list(), 1.0)
+        .with_flex_child(
+            ViewSwitcher::new(
+                |data: &State, _env: &Env| charted_kinds(data),
+                |mask, _data, _env| Box::new(sensor_graphs(*mask)),
+            ),
+            2.0,
+        )
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+}
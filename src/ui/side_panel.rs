@@ -1,13 +1,138 @@
-use druid::widget::{Flex, Label};
-use druid::{Env, WidgetExt};
+use druid::widget::{Checkbox, CrossAxisAlignment, Flex, Label, LineBreaking, Slider, Stepper};
+use druid::{lens, Env, Widget, WidgetExt};
+use crate::cgroups::{effective_limits, Limits};
+use crate::history::percent;
+use crate::processes::format_duration;
+use crate::settings::{MAX_HISTORY_SIZE, MIN_HISTORY_SIZE, MIN_INTERVAL_MS};
 use crate::State;
+use crate::ui::usage_graph::format_bytes;
+
+const MAX_INTERVAL_MS: f64 = 5000.0;
 
 pub (crate) fn side_panel() -> Flex<State> {
     // Side panel
     Flex::column()
         .with_spacer(10.0)
         .with_child(Label::new("System Info").expand_width())
-        .with_child(Label::new(|_data: &State, _env: &Env| {
-            "CPU Usage (average)".to_string()
+        .with_child(info(host_summary))
+        .with_spacer(10.0)
+        .with_child(info(load_summary))
+        .with_spacer(10.0)
+        .with_child(info(usage_summary))
+        .with_spacer(10.0)
+        .with_child(limits_controls())
+        .with_spacer(20.0)
+        .with_child(sampling_controls())
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+}
+
+// A wrapped block of text, rebuilt from the state on every update
+fn info(text: fn(&State) -> String) -> impl Widget<State> {
+    Label::new(move |data: &State, _env: &Env| text(data))
+        .with_line_break_mode(LineBreaking::WordWrap)
+        .expand_width()
+}
+
+fn host_summary(data: &State) -> String {
+    let host = &data.system.host;
+    let cores = match host.physical_cores {
+        Some(physical) => format!("{} cores, {} threads", physical, host.logical_cores),
+        None => format!("{} threads", host.logical_cores),
+    };
+    format!(
+        "Host: {}\nOS: {}\nKernel: {}\nCPU: {}\n{}",
+        host.host_name, host.os_version, host.kernel_version, host.cpu_model, cores
+    )
+}
+
+fn load_summary(data: &State) -> String {
+    let load = &data.system.load;
+    format!(
+        "Uptime: {}\nLoad: {:.2} {:.2} {:.2}\nTasks: {} running / {} total",
+        format_duration(data.system.uptime), load.one, load.five, load.fifteen, load.running_tasks, load.total_tasks
+    )
+}
+
+// Current CPU, RAM and GPU readings, the same values that head the overview graphs
+fn usage_summary(data: &State) -> String {
+    let system = &data.system;
+    let limits = data.active_limits();
+    let mut lines = vec![
+        match limits {
+            Some((group, Limits { cpus: Some(cpus), .. })) => {
+                format!("CPU: {:.1}% of quota", percent(group.cpu_history.latest_or_zero(), cpus * 100.0))
+            }
+            _ => format!("CPU: {:.1}%", system.cpu_avg_history.latest_or_zero()),
+        },
+        match limits {
+            Some((group, Limits { memory: Some(max), .. })) => format!(
+                "RAM: {} / {} limit",
+                format_bytes(group.memory_history.latest_or_zero()), format_bytes(max)
+            ),
+            _ => format!("RAM: {:.2} GB / {:.2} GB", system.used_mem / 1024.0, system.total_mem / 1024.0),
+        },
+    ];
+    for gpu in data.gpus.iter() {
+        lines.push(format!(
+            "GPU {}: {:.0}% · {:.0}°C",
+            gpu.index, gpu.util_history.latest_or_zero(), gpu.temp_history.latest_or_zero()
+        ));
+    }
+    lines.join("\n")
+}
+
+// Container-aware mode, which shows CPU and RAM against a cgroup's cpu.max and memory.max
+fn limits_controls() -> Flex<State> {
+    Flex::column()
+        .with_child(Checkbox::new("Relative to cgroup limits").lens(State::use_limits))
+        .with_child(info(|data| {
+            let Some(path) = &data.limits_cgroup else { return "No cgroup v2 group found".to_string() };
+            if !data.cgroups.iter().any(|group| &group.path == path) {
+                return format!("{}: not found", path);
+            }
+            let limits = effective_limits(&data.cgroups, path);
+            let cpus = limits.cpus.map(|cpus| format!("{:.2} CPUs", cpus)).unwrap_or_else(|| "no CPU quota".to_string());
+            let memory = limits.memory.map(format_bytes).unwrap_or_else(|| "no memory limit".to_string());
+            format!("{}: {}, {}", path, cpus, memory)
         }))
-}
\ No newline at end of file
+        .cross_axis_alignment(CrossAxisAlignment::Start)
+}
+
+// Sample interval and history length; changes are picked up by the samplers on their next tick
+fn sampling_controls() -> Flex<State> {
+    let interval = lens::Map::new(
+        |data: &State| data.settings.interval_ms() as f64,
+        |data: &mut State, ms: f64| data.settings.set_interval_ms(ms as u64),
+    );
+    let history = lens::Map::new(
+        |data: &State| data.settings.history_size() as f64,
+        |data: &mut State, samples: f64| data.settings.set_history_size(samples as usize),
+    );
+
+    Flex::column()
+        .with_child(Label::new("Sampling").expand_width())
+        .with_child(
+            Flex::row()
+                .with_flex_child(Label::new(|data: &State, _env: &Env| {
+                    format!("Interval: {} ms", data.settings.interval_ms())
+                }), 1.0)
+                .with_child(
+                    Stepper::new()
+                        .with_range(MIN_INTERVAL_MS as f64, MAX_INTERVAL_MS)
+                        .with_step(50.0)
+                        .lens(interval),
+                ),
+        )
+        .with_child(Label::new(|data: &State, _env: &Env| {
+            let samples = data.settings.history_size();
+            let seconds = samples as f64 * data.settings.interval_ms() as f64 / 1000.0;
+            format!("History: {} samples ({:.0} s)", samples, seconds)
+        }).expand_width())
+        .with_child(
+            Slider::new()
+                .with_range(MIN_HISTORY_SIZE as f64, MAX_HISTORY_SIZE as f64)
+                .with_step(10.0)
+                .lens(history)
+                .expand_width(),
+        )
+}
@@ -0,0 +1,70 @@
+use std::cmp::Ordering;
+use druid::{Data, Env, Widget, WidgetExt};
+use druid::widget::{Button, Label};
+use crate::State;
+
+// Which column a table is sorted by, and in which direction
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct TableSort<C> {
+    pub(crate) column: C,
+    pub(crate) descending: bool,
+}
+
+impl<C: Copy + PartialEq> TableSort<C> {
+    pub(crate) fn new(column: C, descending: bool) -> Self {
+        Self { column, descending }
+    }
+
+    // Clicking the sorted column flips its direction; any other column becomes the sort key
+    pub(crate) fn select(&mut self, column: C) {
+        if self.column == column {
+            self.descending = !self.descending;
+        } else {
+            self.column = column;
+            self.descending = false;
+        }
+    }
+
+    pub(crate) fn sort<T>(&self, rows: &mut [T], compare: impl Fn(C, &T, &T) -> Ordering) {
+        rows.sort_by(|a, b| {
+            let ordering = compare(self.column, a, b);
+            if self.descending { ordering.reverse() } else { ordering }
+        });
+    }
+
+    fn title(&self, column: C, title: &str) -> String {
+        match (self.column == column, self.descending) {
+            (true, true) => format!("{} ▼", title),
+            (true, false) => format!("{} ▲", title),
+            (false, _) => title.to_string(),
+        }
+    }
+}
+
+// Order floats that may be missing, with missing values sorting first
+pub(crate) fn compare_f64(a: Option<f64>, b: Option<f64>) -> Ordering {
+    a.partial_cmp(&b).unwrap_or(Ordering::Equal)
+}
+
+// Column header that sorts the table by `column` when clicked, then rebuilds its rows with `update`
+pub(crate) fn header<C: Copy + PartialEq + 'static>(
+    title: &'static str,
+    column: C,
+    width: f64,
+    sort: fn(&mut State) -> &mut TableSort<C>,
+    current: fn(&State) -> &TableSort<C>,
+    update: fn(&mut State),
+) -> impl Widget<State> {
+    Button::dynamic(move |data: &State, _env: &Env| current(data).title(column, title))
+        .on_click(move |_ctx, data: &mut State, _env| {
+            sort(data).select(column);
+            update(data);
+        })
+        .fix_width(width)
+}
+
+// Fixed-width table cell so rows line up under their headers
+pub(crate) fn cell<T: Data>(width: f64, text: impl Fn(&T) -> String + 'static) -> impl Widget<T> {
+    Label::new(move |row: &T, _env: &Env| text(row))
+        .fix_width(width)
+}
@@ -1,9 +1,15 @@
 use druid::{BoxConstraints, Color, Env, Event, EventCtx, FontFamily, LayoutCtx, LifeCycle, LifeCycleCtx, PaintCtx, Rect, RenderContext, Size, UpdateCtx, Widget};
 use druid::kurbo::{BezPath, Line};
 use druid::piet::{Text, TextLayout, TextLayoutBuilder};
-use im::Vector;
-use crate::{State, HISTORY_SIZE, UPDATE_GPU, UPDATE_METRICS};
-use crate::gpu::MAX_RPM;
+use crate::State;
+use crate::cgroups::Limits;
+use crate::diskio::BlockDevice;
+use crate::gpu::{GPU, MAX_RPM};
+use crate::history::{percent, TimeSeries};
+use crate::sensors::SensorKind;
+use crate::system::{CPU_STATES, MEMORY_CATEGORIES};
+use crate::network::NetworkInterface;
+use crate::pressure::Resource;
 
 const FONT_SIZE: f64 = 10.0;
 const LABEL_COLOUR: Color = Color::grey8(220);
@@ -27,10 +33,207 @@ const COLOURS: [Color; 12] = [
 pub enum PlotType {
     AverageCPU,
     PerCoreCPU,
+    CpuFrequency,
+    // Stacked CPU time breakdown across all cores, or of one core
+    CpuTime(Option<usize>),
     RAM,
+    Swap,
+    SwapActivity,
     GPU,
     GPUFan,
-    GPUTemp
+    GPUTemp,
+    GPUUtil,
+    GPUPower,
+    GPUClock,
+    GPUPcie,
+    NetworkBytes,
+    NetworkPackets,
+    DiskThroughput,
+    DiskIops,
+    DiskAwait,
+    DiskUtil,
+    Sensors(SensorKind),
+    Pressure(Resource),
+    // Usage of the cgroup selected in the cgroup browser
+    CgroupCpu,
+    CgroupMemory,
+    CgroupIo,
+}
+
+impl PlotType {
+    // Value at the top of the y axis for the GPU plot types, in the plot's own unit
+    pub(crate) fn gpu_axis_max(self, gpus: &[&GPU]) -> f64 {
+        let peak = |histories: &dyn Fn(&GPU) -> Vec<&TimeSeries>| {
+            gpus.iter()
+                .flat_map(|gpu| histories(gpu))
+                .map(|history| history.max())
+                .fold(0.0, f64::max)
+        };
+        match self {
+            PlotType::GPUFan => MAX_RPM as f64,
+            PlotType::GPUPower => {
+                // Scale to the largest enforced limit, or to the peak draw when no limit is reported
+                let limit = gpus.iter().map(|gpu| gpu.power_limit).fold(0.0, f64::max);
+                if limit > 0.0 { limit } else { nice_ceiling(peak(&|gpu| vec![&gpu.power_history])) }
+            }
+            PlotType::GPUClock => nice_ceiling(peak(&|gpu| vec![&gpu.clock_history, &gpu.mem_clock_history])),
+            PlotType::GPUPcie => nice_ceiling(peak(&|gpu| vec![&gpu.pcie_rx_history, &gpu.pcie_tx_history])),
+            _ => 100.0,
+        }
+    }
+
+    // Labelled series drawn for the GPU plot types, scaled to the 0..100 plot range of `axis_max`
+    pub(crate) fn gpu_series(self, gpu: &GPU, axis_max: f64) -> Vec<(String, TimeSeries)> {
+        let scaled = |label: &str, history: &TimeSeries| {
+            (label.to_string(), history.map(|v| percent(v, axis_max)))
+        };
+        match self {
+            PlotType::GPU => vec![("VRAM".to_string(), gpu.used_mem_history.clone())],
+            // Fan speeds are plotted as a percentage of MAX_RPM
+            PlotType::GPUFan => gpu.fan_speed_history.iter().enumerate()
+                .map(|(i, history)| scaled(&format!("Fan {}", i + 1), history))
+                .collect(),
+            // temp_history already stores temperatures in °C, which fit the 0..100 scale
+            PlotType::GPUTemp => vec![("Temperature".to_string(), gpu.temp_history.clone())],
+            PlotType::GPUUtil => vec![
+                ("SM".to_string(), gpu.util_history.clone()),
+                ("Memory".to_string(), gpu.mem_util_history.clone()),
+            ],
+            PlotType::GPUPower => vec![scaled("Power", &gpu.power_history)],
+            PlotType::GPUClock => vec![
+                scaled("Graphics", &gpu.clock_history),
+                scaled("Memory", &gpu.mem_clock_history),
+            ],
+            PlotType::GPUPcie => vec![
+                scaled("RX", &gpu.pcie_rx_history),
+                scaled("TX", &gpu.pcie_tx_history),
+            ],
+            _ => Vec::new(),
+        }
+    }
+
+    // Y axis range of the frequency graph: the cpufreq limits, or zero to the peak clock when
+    // cpufreq isn't available (as in most VMs)
+    fn frequency_range(data: &State) -> (f64, f64) {
+        let system = &data.system;
+        if system.cpu_freq_max > system.cpu_freq_min {
+            return (system.cpu_freq_min, system.cpu_freq_max);
+        }
+        let peak = system.cpu_freq_history.iter().map(|history| history.max()).fold(0.0, f64::max);
+        (0.0, nice_ceiling(peak))
+    }
+
+    // Auto-scaled top of the y axis for the network plot types
+    pub(crate) fn network_axis_max(self, interfaces: &[&NetworkInterface]) -> f64 {
+        let peak = interfaces.iter()
+            .flat_map(|interface| match self {
+                PlotType::NetworkPackets => [&interface.rx_packets_history, &interface.tx_packets_history],
+                _ => [&interface.rx_bytes_history, &interface.tx_bytes_history],
+            })
+            .map(|history| history.max())
+            .fold(0.0, f64::max);
+        nice_ceiling(peak)
+    }
+
+    // Both directions of an interface, scaled to the 0..100 plot range of `axis_max`
+    pub(crate) fn network_series(self, interface: &NetworkInterface, axis_max: f64) -> Vec<(String, TimeSeries)> {
+        let (rx, tx) = match self {
+            PlotType::NetworkPackets => (&interface.rx_packets_history, &interface.tx_packets_history),
+            _ => (&interface.rx_bytes_history, &interface.tx_bytes_history),
+        };
+        vec![
+            (format!("{} RX", interface.name), rx.map(|v| percent(v, axis_max))),
+            (format!("{} TX", interface.name), tx.map(|v| percent(v, axis_max))),
+        ]
+    }
+
+    // Top of the y axis for the disk plot types; everything but %util is auto-scaled
+    pub(crate) fn disk_axis_max(self, disks: &[&BlockDevice]) -> f64 {
+        if let PlotType::DiskUtil = self {
+            return 100.0;
+        }
+        let peak = disks.iter()
+            .flat_map(|disk| self.disk_histories(disk))
+            .map(|(_, history)| history.max())
+            .fold(0.0, f64::max);
+        nice_ceiling(peak)
+    }
+
+    fn disk_histories(self, disk: &BlockDevice) -> Vec<(&'static str, &TimeSeries)> {
+        match self {
+            PlotType::DiskThroughput => vec![("read", &disk.read_bytes_history), ("write", &disk.write_bytes_history)],
+            PlotType::DiskIops => vec![("read", &disk.read_iops_history), ("write", &disk.write_iops_history)],
+            PlotType::DiskAwait => vec![("await", &disk.await_history)],
+            PlotType::DiskUtil => vec![("util", &disk.util_history)],
+            _ => Vec::new(),
+        }
+    }
+
+    // Series of one block device, scaled to the 0..100 plot range of `axis_max`
+    pub(crate) fn disk_series(self, disk: &BlockDevice, axis_max: f64) -> Vec<(String, TimeSeries)> {
+        let histories = self.disk_histories(disk);
+        // Single-series plots are labelled by device alone
+        let single = histories.len() == 1;
+        histories.into_iter()
+            .map(|(label, history)| {
+                let label = if single { disk.label() } else { format!("{} {}", disk.label(), label) };
+                (label, history.map(|v| percent(v, axis_max)))
+            })
+            .collect()
+    }
+
+    // Y axis tick label for a value in the plot's own unit
+    fn tick_label(self, value: f64) -> String {
+        match self {
+            PlotType::GPUFan => format!("{:.0}RPM", value),
+            PlotType::GPUTemp => format!("{:.0}°C", value),
+            PlotType::GPUPower => format!("{:.0}W", value),
+            PlotType::GPUClock | PlotType::CpuFrequency => format!("{:.0}MHz", value),
+            PlotType::GPUPcie | PlotType::NetworkBytes | PlotType::DiskThroughput | PlotType::SwapActivity
+            | PlotType::CgroupIo => format!("{}/s", format_bytes(value)),
+            PlotType::CgroupMemory => format_bytes(value),
+            PlotType::NetworkPackets | PlotType::DiskIops => format!("{}/s", format_count(value)),
+            PlotType::DiskAwait => format!("{:.1}ms", value),
+            PlotType::Sensors(kind @ (SensorKind::Temperature | SensorKind::Fan)) => format!("{:.0}{}", value, kind.unit()),
+            PlotType::Sensors(kind) => format!("{:.1}{}", value, kind.unit()),
+            _ => format!("{:.0}%", value),
+        }
+    }
+}
+
+// Round up to the next 1, 2 or 5 times a power of ten, for auto-scaled axes
+pub(crate) fn nice_ceiling(value: f64) -> f64 {
+    if !value.is_finite() || value <= 0.0 {
+        return 1.0;
+    }
+    let magnitude = 10f64.powf(value.log10().floor());
+    [1.0, 2.0, 5.0, 10.0].iter()
+        .map(|step| step * magnitude)
+        .find(|ceiling| *ceiling >= value)
+        .unwrap_or(10.0 * magnitude)
+}
+
+// Human readable byte count using binary units, e.g. "1.5 GB"
+pub(crate) fn format_bytes(bytes: f64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 { format!("{:.0} {}", value, UNITS[unit]) } else { format!("{:.1} {}", value, UNITS[unit]) }
+}
+
+// Short count for axis labels, e.g. "1.5k" or "12M"
+pub(crate) fn format_count(count: f64) -> String {
+    if count >= 1e6 {
+        format!("{:.1}M", count / 1e6)
+    } else if count >= 1e3 {
+        format!("{:.1}k", count / 1e3)
+    } else {
+        format!("{:.0}", count)
+    }
 }
 
 // Custom widget for per-core CPU graph
@@ -45,22 +248,26 @@ impl UsageGraph {
         }
     }
 
-    fn draw_line(ctx: &mut PaintCtx, plot_rect: Rect, color: &Color, history: Vector<f64>) {
+    fn draw_line(ctx: &mut PaintCtx, plot_rect: Rect, color: &Color, history: &TimeSeries) {
+        if history.is_empty() {
+            return;
+        }
         let mut path = BezPath::new();
-        let x_start = plot_rect.x0;
         let width = plot_rect.width();
         let height = plot_rect.height();
         let y_base = plot_rect.y1;
-        let scale_x = width / (HISTORY_SIZE.saturating_sub(1) as f64);
+        // Space samples by the series capacity so a partly filled history grows in from the right
+        let scale_x = width / (history.capacity().saturating_sub(1).max(1) as f64);
+        let x_start = plot_rect.x1 - (history.len() - 1) as f64 * scale_x;
 
-        // Start at the first history sample aligned to the left edge
-        path.move_to((x_start, y_base - (history[0] / 100.0) * height));
-
-        // Plot each subsequent point within the plotting rect, so it aligns with the axes
-        for (x, &val) in history.iter().enumerate().skip(1) {
-            let x_pos = x_start + (x as f64) * scale_x;
-            let y = y_base - (val / 100.0) * height;
-            path.line_to((x_pos, y));
+        // Start at the oldest sample and plot each subsequent point within the plotting rect
+        for (x, val) in history.iter().enumerate() {
+            let point = (x_start + (x as f64) * scale_x, y_base - (val / 100.0) * height);
+            if x == 0 {
+                path.move_to(point);
+            } else {
+                path.line_to(point);
+            }
         }
 
         ctx.stroke(path.clone(), color, 2.0);
@@ -68,11 +275,81 @@ impl UsageGraph {
         // Fill down to the X axis within the plotting area
         let mut fill = path.clone();
         fill.line_to((plot_rect.x1, plot_rect.y1));
-        fill.line_to((plot_rect.x0, plot_rect.y1));
+        fill.line_to((x_start, plot_rect.y1));
         fill.close_path();
         ctx.fill(fill, &color.with_alpha(0.15));
     }
 
+    // Label the X axis with the time covered by a full history, estimated from the sample timestamps
+    fn draw_time_axis(ctx: &mut PaintCtx, plot_rect: Rect, history: &TimeSeries) {
+        if history.len() < 2 {
+            return;
+        }
+        let per_sample = history.span().as_secs_f64() / (history.len() - 1) as f64;
+        let window = per_sample * (history.capacity() - 1) as f64;
+        let labels = [(format!("-{:.0}s", window), plot_rect.x0), ("now".to_string(), plot_rect.x1 - 20.0)];
+        for (label, x) in labels {
+            let text = ctx.text()
+                .new_text_layout(label)
+                .text_color(LABEL_COLOUR)
+                .font(FontFamily::SYSTEM_UI, FONT_SIZE)
+                .build()
+                .unwrap();
+            ctx.draw_text(&text, (x, plot_rect.y1 + 6.0));
+        }
+    }
+
+    // Series stacked on top of each other as filled areas, first series at the bottom
+    fn draw_stacked(ctx: &mut PaintCtx, plot_rect: Rect, series: &[(String, TimeSeries)], legend: bool) {
+        let Some((_, first)) = series.first() else { return };
+        if first.is_empty() {
+            return;
+        }
+        let scale_x = plot_rect.width() / (first.capacity().saturating_sub(1).max(1) as f64);
+        let x_start = plot_rect.x1 - (first.len() - 1) as f64 * scale_x;
+        let point = |x: usize, value: f64| {
+            (x_start + x as f64 * scale_x, plot_rect.y1 - (value / 100.0).min(1.0) * plot_rect.height())
+        };
+
+        let mut lower = vec![0.0; first.len()];
+        for (i, (_, history)) in series.iter().enumerate() {
+            let upper: Vec<f64> = lower.iter().zip(history.iter()).map(|(base, value)| base + value).collect();
+            // Trace the top edge left to right, then the layer below back right to left
+            let mut path = BezPath::new();
+            for (x, value) in upper.iter().enumerate() {
+                if x == 0 { path.move_to(point(x, *value)) } else { path.line_to(point(x, *value)) }
+            }
+            for (x, value) in lower.iter().enumerate().take(upper.len()).rev() {
+                path.line_to(point(x, *value));
+            }
+            path.close_path();
+            ctx.fill(path, &COLOURS[i % COLOURS.len()].with_alpha(0.7));
+            lower = upper;
+        }
+
+        if !legend {
+            return;
+        }
+        let items: Vec<(String, Color)> = series.iter().enumerate()
+            .map(|(i, (label, _))| (label.clone(), COLOURS[i % COLOURS.len()]))
+            .collect();
+        UsageGraph::draw_legends(ctx, plot_rect, plot_rect.x0 + 10.0, plot_rect.y0 + 10.0, 16.0, 20.0, &items);
+    }
+
+    // Labelled series in consecutive colours starting at `first_colour`, with an optional legend
+    fn draw_series(ctx: &mut PaintCtx, plot_rect: Rect, series: &[(String, TimeSeries)], first_colour: usize, legend: bool) {
+        if legend && !series.is_empty() {
+            let items: Vec<(String, Color)> = series.iter().enumerate()
+                .map(|(i, (label, _))| (label.clone(), COLOURS[(first_colour + i) % COLOURS.len()]))
+                .collect();
+            UsageGraph::draw_legends(ctx, plot_rect, plot_rect.x0 + 10.0, plot_rect.y0 + 10.0, 16.0, 20.0, &items);
+        }
+        for (i, (_, history)) in series.iter().enumerate() {
+            let colour = &COLOURS[(first_colour + i) % COLOURS.len()];
+            UsageGraph::draw_line(ctx, plot_rect, colour, history);
+        }
+    }
+
     fn draw_legends(ctx: &mut PaintCtx, plot_rect: Rect, legend_x: f64, legend_y: f64, item_height: f64, text_offset: f64, items: &[(String, Color)]) {
         let mut x = legend_x;
         let mut y = legend_y;
@@ -118,27 +395,15 @@ impl UsageGraph {
 }
 
 impl Widget<State> for UsageGraph {
-    fn event(&mut self, ctx: &mut EventCtx, event: &Event, data: &mut State, env: &Env) {
-        if let Event::Command(cmd) = event {
-            if let Some(new_stats) = cmd.get(UPDATE_METRICS) {
-                // Replace whole state (or selectively update fields)
-                data.system.cpu_history = new_stats.cpu_history.clone();
-                data.system.cpu_avg_history = new_stats.cpu_avg_history.clone();
-                data.system.used_mem_history = new_stats.used_mem_history.clone();
-                data.system.used_mem = new_stats.used_mem;
-                data.system.total_mem = new_stats.total_mem;
-                ctx.request_paint();
-            } else if let Some(new_gpu) = cmd.get(UPDATE_GPU) {
-                data.gpu = new_gpu.clone();
-                ctx.request_paint();
-            }
-        }
+    fn event(&mut self, _ctx: &mut EventCtx, _event: &Event, _data: &mut State, _env: &Env) {
     }
 
     fn lifecycle(&mut self, ctx: &mut LifeCycleCtx, event: &LifeCycle, data: &State, env: &Env) {
     }
 
-    fn update(&mut self, ctx: &mut UpdateCtx, old_data: &State, data: &State, env: &Env) {
+    fn update(&mut self, ctx: &mut UpdateCtx, _old_data: &State, _data: &State, _env: &Env) {
+        // New samples arrive through the app delegate
+        ctx.request_paint();
     }
 
     fn layout(&mut self, ctx: &mut LayoutCtx, bc: &BoxConstraints, data: &State, env: &Env) -> Size {
@@ -147,7 +412,7 @@ impl Widget<State> for UsageGraph {
 
     fn paint(&mut self, ctx: &mut PaintCtx, data: &State, env: &Env) {
         let size = ctx.size();
-        let left_margin = 40.0;
+        let left_margin = 60.0;
         let bottom_margin = 30.0;
         let plot_rect = Rect::new(left_margin, 10.0, size.width - 10.0, size.height - bottom_margin);
 
@@ -165,20 +430,59 @@ impl Widget<State> for UsageGraph {
             (plot_rect.x0, plot_rect.y1),
         ), &axis_color, 2.0); // Y axis
 
+        let axis_max = match self.plot_type {
+            PlotType::GPU | PlotType::GPUFan | PlotType::GPUTemp | PlotType::GPUUtil
+            | PlotType::GPUPower | PlotType::GPUClock | PlotType::GPUPcie => {
+                self.plot_type.gpu_axis_max(&data.visible_gpus())
+            }
+            PlotType::NetworkBytes | PlotType::NetworkPackets => {
+                self.plot_type.network_axis_max(&data.visible_interfaces())
+            }
+            PlotType::DiskThroughput | PlotType::DiskIops | PlotType::DiskAwait | PlotType::DiskUtil => {
+                self.plot_type.disk_axis_max(&data.disks.iter().collect::<Vec<_>>())
+            }
+            PlotType::SwapActivity => {
+                nice_ceiling(data.system.swap_in_history.max().max(data.system.swap_out_history.max()))
+            }
+            PlotType::CpuFrequency => PlotType::frequency_range(data).1,
+            PlotType::Sensors(kind) => {
+                let peak = nice_ceiling(data.sensors_to_chart(kind).iter().map(|sensor| sensor.history.max()).fold(0.0, f64::max));
+                // Keep temperatures on a familiar 0..100°C scale unless something runs hotter
+                if kind == SensorKind::Temperature { peak.max(100.0) } else { peak }
+            }
+            PlotType::Pressure(resource) => {
+                // Stalls are usually a few percent at most, so zoom in on them
+                let peak = data.resource_pressure(resource)
+                    .map(|pressure| pressure.some_history.max().max(pressure.full_history.max()))
+                    .unwrap_or(0.0);
+                nice_ceiling(peak).min(100.0)
+            }
+            // A busy group can use several CPUs, so the axis grows past 100% of one
+            PlotType::CgroupCpu => {
+                nice_ceiling(data.current_cgroup().map(|group| group.cpu_history.max()).unwrap_or(0.0)).max(100.0)
+            }
+            // Scale to the group's limit when it has one, to show the headroom left
+            PlotType::CgroupMemory => match data.current_cgroup() {
+                Some(group) => group.memory_max.unwrap_or_else(|| nice_ceiling(group.memory_history.max())),
+                None => 1.0,
+            },
+            PlotType::CgroupIo => {
+                let peak = data.current_cgroup()
+                    .map(|group| group.io_read_history.max().max(group.io_write_history.max()))
+                    .unwrap_or(0.0);
+                nice_ceiling(peak)
+            }
+            _ => 100.0,
+        };
+        // Only the frequency graph has an axis that doesn't start at zero
+        let axis_min = match self.plot_type {
+            PlotType::CpuFrequency => PlotType::frequency_range(data).0,
+            _ => 0.0,
+        };
+
         for i in 0..=10 {
             let y = plot_rect.y1 - (i as f64) * (plot_rect.height() / 10.0);
-            let label = match self.plot_type {
-                PlotType::GPUFan => {
-                    let step = MAX_RPM / 10; // 300
-                    format!("{}RPM", i * step)
-                }
-                PlotType::GPUTemp => {
-                    format!("{}°C", i * 10)
-                }
-                _ => {
-                    format!("{}%", i * 10)
-                }
-            };
+            let label = self.plot_type.tick_label(axis_min + (axis_max - axis_min) * i as f64 / 10.0);
             ctx.stroke(Line::new(
                 (plot_rect.x0 - 5.0, y),
                 (plot_rect.x0, y),
@@ -197,15 +501,23 @@ impl Widget<State> for UsageGraph {
             );
         }
 
+        // Every sampler shares the same interval, so the CPU history stands in for all graphs
+        UsageGraph::draw_time_axis(ctx, plot_rect, &data.system.cpu_avg_history);
+
         let legend_x = plot_rect.x0 + 10.0;
         let legend_y = plot_rect.y0 + 10.0;
         let item_height = 16.0;
         let text_offset = 20.0;
 
         match self.plot_type {
-            PlotType::AverageCPU => {
-                UsageGraph::draw_line(ctx, plot_rect.clone(), &COLOURS[1], data.system.cpu_avg_history.clone());
-            }
+            PlotType::AverageCPU => match data.active_limits() {
+                // The group's own usage as a share of its CPU quota
+                Some((group, Limits { cpus: Some(cpus), .. })) => {
+                    let history = group.cpu_history.map(|v| percent(v, cpus * 100.0));
+                    UsageGraph::draw_line(ctx, plot_rect, &COLOURS[1], &history);
+                }
+                _ => UsageGraph::draw_line(ctx, plot_rect, &COLOURS[1], &data.system.cpu_avg_history),
+            },
             PlotType::PerCoreCPU => {
                 let mut items: Vec<(String, Color)> = Vec::new();
                 for i in 0..data.system.cpu_history.len() {
@@ -215,40 +527,113 @@ impl Widget<State> for UsageGraph {
                 UsageGraph::draw_legends(ctx, plot_rect, legend_x, legend_y, item_height, text_offset, &items);
                 for (i, core_history) in data.system.cpu_history.iter().enumerate() {
                     let colour = &COLOURS[i % COLOURS.len()];
-                    UsageGraph::draw_line(ctx, plot_rect.clone(), &colour, core_history.clone());
+                    UsageGraph::draw_line(ctx, plot_rect, colour, core_history);
                 }
             }
-            PlotType::RAM => {
-                UsageGraph::draw_line(ctx, plot_rect.clone(), &COLOURS[2], data.system.used_mem_history.clone());
+            PlotType::CpuFrequency => {
+                let series: Vec<(String, TimeSeries)> = data.system.cpu_freq_history.iter().enumerate()
+                    .map(|(i, history)| {
+                        let scaled = history.map(|v| percent(v - axis_min, axis_max - axis_min));
+                        (format!("Core {}", i + 1), scaled)
+                    })
+                    .collect();
+                UsageGraph::draw_series(ctx, plot_rect, &series, 0, true);
             }
-            PlotType::GPU => {
-                UsageGraph::draw_line(ctx, plot_rect.clone(), &COLOURS[3], data.gpu.used_mem_history.clone())
+            PlotType::Sensors(kind) => {
+                let series: Vec<(String, TimeSeries)> = data.sensors_to_chart(kind).into_iter()
+                    .map(|sensor| (format!("{} {}", sensor.chip, sensor.label), sensor.history.map(|v| percent(v, axis_max))))
+                    .collect();
+                UsageGraph::draw_series(ctx, plot_rect, &series, 0, true);
             }
-            PlotType::GPUFan => {
-                // For each GPU fan, convert RPM history to percentage of 3000 RPM for plotting
-                let mut items: Vec<(String, Color)> = Vec::new();
-                for i in 0..data.gpu.fan_speed_history.len() {
-                    let colour = COLOURS[i % COLOURS.len()].clone();
-                    items.push((format!("Fan {}", i + 1), colour));
+            PlotType::RAM => match data.active_limits() {
+                // /proc/meminfo describes the host, so only the group's total is shown against its limit
+                Some((group, Limits { memory: Some(max), .. })) => {
+                    let history = group.memory_history.map(|v| percent(v, max));
+                    UsageGraph::draw_line(ctx, plot_rect, &COLOURS[0], &history);
                 }
-                if !items.is_empty() {
-                    UsageGraph::draw_legends(ctx, plot_rect, legend_x, legend_y, item_height, text_offset, &items);
+                _ => {
+                    // Stacked breakdown as a percentage of total memory
+                    let total = data.system.total_mem * 1024.0 * 1024.0;
+                    let series: Vec<(String, TimeSeries)> = MEMORY_CATEGORIES.iter()
+                        .zip(data.system.memory_breakdown.iter())
+                        .map(|(category, history)| (category.to_string(), history.map(|v| percent(v, total))))
+                        .collect();
+                    UsageGraph::draw_stacked(ctx, plot_rect, &series, true);
                 }
-                for (i, fan_history) in data.gpu.fan_speed_history.iter().enumerate() {
-                    let colour = &COLOURS[i % COLOURS.len()];
-                    let mut v: Vec<f64> = Vec::with_capacity(fan_history.len());
-                    for val in fan_history.iter() {
-                        let pct = if MAX_RPM as f64 > 0.0 { (val / MAX_RPM as f64) * 100.0 } else { 0.0 };
-                        let pct = if pct.is_finite() { pct.max(0.0).min(100.0) } else { 0.0 };
-                        v.push(pct);
+            },
+            PlotType::CpuTime(core) => {
+                let histories = match core {
+                    Some(core) => data.system.core_time_breakdown.get(core),
+                    None => Some(&data.system.cpu_time_breakdown),
+                };
+                let series: Vec<(String, TimeSeries)> = CPU_STATES.iter()
+                    .zip(histories.into_iter().flatten())
+                    .map(|(state, history)| (state.to_string(), history.clone()))
+                    .collect();
+                // The per-core graphs are too small for a legend of their own
+                UsageGraph::draw_stacked(ctx, plot_rect, &series, core.is_none());
+            }
+            PlotType::Pressure(resource) => {
+                let Some(pressure) = data.resource_pressure(resource) else { return };
+                let mut series = vec![("some".to_string(), pressure.some_history.map(|v| percent(v, axis_max)))];
+                if pressure.latest.full.is_some() {
+                    series.push(("full".to_string(), pressure.full_history.map(|v| percent(v, axis_max))));
+                }
+                UsageGraph::draw_series(ctx, plot_rect, &series, 2, true);
+            }
+            PlotType::CgroupCpu | PlotType::CgroupMemory | PlotType::CgroupIo => {
+                let Some(group) = data.current_cgroup() else { return };
+                let series = match self.plot_type {
+                    PlotType::CgroupCpu => vec![("CPU".to_string(), &group.cpu_history)],
+                    PlotType::CgroupMemory => vec![("Memory".to_string(), &group.memory_history)],
+                    _ => vec![("Read".to_string(), &group.io_read_history), ("Write".to_string(), &group.io_write_history)],
+                };
+                let series: Vec<(String, TimeSeries)> = series.into_iter()
+                    .map(|(label, history)| (label, history.map(|v| percent(v, axis_max))))
+                    .collect();
+                UsageGraph::draw_series(ctx, plot_rect, &series, 0, series.len() > 1);
+            }
+            PlotType::Swap => {
+                UsageGraph::draw_line(ctx, plot_rect, &COLOURS[3], &data.system.used_swap_history);
+            }
+            PlotType::SwapActivity => {
+                let series = vec![
+                    ("Swap in".to_string(), data.system.swap_in_history.map(|v| percent(v, axis_max))),
+                    ("Swap out".to_string(), data.system.swap_out_history.map(|v| percent(v, axis_max))),
+                ];
+                UsageGraph::draw_series(ctx, plot_rect, &series, 0, true);
+            }
+            PlotType::GPU | PlotType::GPUFan | PlotType::GPUTemp | PlotType::GPUUtil
+            | PlotType::GPUPower | PlotType::GPUClock | PlotType::GPUPcie => {
+                // When comparing several GPUs, prefix each series with its device index
+                let gpus = data.visible_gpus();
+                let compare = gpus.len() > 1;
+                let mut series: Vec<(String, TimeSeries)> = Vec::new();
+                for gpu in gpus {
+                    for (label, history) in self.plot_type.gpu_series(gpu, axis_max) {
+                        let label = if compare { format!("GPU {} {}", gpu.index, label) } else { label };
+                        series.push((label, history));
                     }
-                    let pct_history = Vector::from(v);
-                    UsageGraph::draw_line(ctx, plot_rect.clone(), &colour, pct_history);
                 }
+                let first_colour = match (self.plot_type, compare) {
+                    (PlotType::GPU, false) => 3,
+                    (PlotType::GPUTemp, false) => 4,
+                    _ => 0,
+                };
+                let legend = series.len() > 1 || matches!(self.plot_type, PlotType::GPUFan);
+                UsageGraph::draw_series(ctx, plot_rect, &series, first_colour, legend);
+            }
+            PlotType::NetworkBytes | PlotType::NetworkPackets => {
+                let series: Vec<(String, TimeSeries)> = data.visible_interfaces().into_iter()
+                    .flat_map(|interface| self.plot_type.network_series(interface, axis_max))
+                    .collect();
+                UsageGraph::draw_series(ctx, plot_rect, &series, 0, true);
             }
-            PlotType::GPUTemp => {
-                // temp_history already stores temperatures in °C; draw_line expects values on 0..100 scale
-                UsageGraph::draw_line(ctx, plot_rect.clone(), &COLOURS[4], data.gpu.temp_history.clone());
+            PlotType::DiskThroughput | PlotType::DiskIops | PlotType::DiskAwait | PlotType::DiskUtil => {
+                let series: Vec<(String, TimeSeries)> = data.disks.iter()
+                    .flat_map(|disk| self.plot_type.disk_series(disk, axis_max))
+                    .collect();
+                UsageGraph::draw_series(ctx, plot_rect, &series, 0, true);
             }
         };
 